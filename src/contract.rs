@@ -1,16 +1,38 @@
 #[cfg(not(feature = "library"))]
 use crate::error::ContractError;
-use crate::execute::buy_squares::buy_squares;
+use crate::execute::add_invite_code::add_invite_code;
+use crate::execute::assign_digits::assign_digits;
+use crate::execute::buy_squares::{buy_squares, buy_squares_with_allowance};
 use crate::execute::choose_winner::choose_winner;
 use crate::execute::claim_refund::claim_refund;
+use crate::execute::claim_winnings::claim_winnings;
+use crate::execute::contribute::contribute;
+use crate::execute::finalize::finalize;
+use crate::execute::join_with_code::join_with_code;
+use crate::execute::receive::receive;
 use crate::execute::register_player::register_player;
+use crate::execute::register_players::register_players;
+use crate::execute::remove_invite_code::remove_invite_code;
 use crate::execute::start_game::start_game;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::execute::sweep::sweep;
+use crate::execute::unregister_player::unregister_player;
+use crate::execute::update_profile::update_profile;
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, SquaresOrder};
+use crate::nft::{build_instantiate_nft_msg, handle_instantiate_nft_reply, INSTANTIATE_NFT_REPLY_ID};
+use crate::query::query_cell::query_cell;
+use crate::query::query_claimable::query_claimable;
+use crate::query::query_contributions::query_contributions;
 use crate::query::query_game::query_game;
+use crate::query::query_grid::query_grid;
+use crate::query::query_ledger::query_ledger;
+use crate::query::query_player::query_player;
+use crate::query::query_players::query_players;
+use crate::query::query_referrals::query_referrals;
 use crate::state;
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult};
 use cw2::set_contract_version;
+use cw_utils::nonpayable;
 
 const CONTRACT_NAME: &str = "crates.io:cw-acl";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -24,7 +46,27 @@ pub fn instantiate(
 ) -> Result<Response, ContractError> {
   set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
   state::initialize(deps, &env, &info, &msg)?;
-  Ok(Response::new().add_attribute("action", "instantiate"))
+
+  let mut resp = Response::new().add_attribute("action", "instantiate");
+
+  // instantiate the companion cw721 contract, if squares are NFTs
+  if let Some(nft_config) = &msg.nft {
+    resp = resp.add_submessage(build_instantiate_nft_msg(&env, &info.sender, nft_config)?);
+  }
+
+  Ok(resp)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(
+  deps: DepsMut,
+  _env: Env,
+  msg: Reply,
+) -> Result<Response, ContractError> {
+  match msg.id {
+    INSTANTIATE_NFT_REPLY_ID => handle_instantiate_nft_reply(deps, msg),
+    id => Err(StdError::generic_err(format!("unknown reply id: {}", id)).into()),
+  }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -34,16 +76,77 @@ pub fn execute(
   info: MessageInfo,
   msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+  // only messages paid for with native funds accept them
+  match &msg {
+    ExecuteMsg::BuySquares { .. } | ExecuteMsg::Contribute {} => {},
+    _ => nonpayable(&info)?,
+  }
+
   match msg {
     ExecuteMsg::RegisterPlayer { wallet, name, color } => register_player(deps, env, info, &wallet, name, color),
+    ExecuteMsg::RegisterPlayers { players } => register_players(deps, env, info, players),
+    ExecuteMsg::UnregisterPlayer { wallet } => unregister_player(deps, env, info, &wallet),
+    ExecuteMsg::AddInviteCode {
+      hash,
+      max_uses,
+      expires_at,
+    } => add_invite_code(deps, env, info, &hash, max_uses, expires_at),
+    ExecuteMsg::RemoveInviteCode { hash } => remove_invite_code(deps, env, info, &hash),
+    ExecuteMsg::JoinWithCode { code, name, color } => join_with_code(deps, env, info, &code, name, color),
+    ExecuteMsg::UpdateProfile { name, color } => update_profile(deps, env, info, name, color),
     ExecuteMsg::StartGame {} => start_game(deps, env, info),
+    ExecuteMsg::AssignDigits { rows, cols } => assign_digits(deps, env, info, rows, cols),
     ExecuteMsg::BuySquares {
       coordinates,
+      shares,
+      player_name,
+      player_color,
+      merkle_proof,
+      recipient,
+      referrer,
+    } => buy_squares(
+      deps,
+      env,
+      info,
+      SquaresOrder {
+        coordinates,
+        shares,
+        player_name,
+        player_color,
+        merkle_proof,
+        recipient,
+        referrer,
+      },
+    ),
+    ExecuteMsg::BuySquaresWithAllowance {
+      coordinates,
+      shares,
       player_name,
       player_color,
-    } => buy_squares(deps, env, info, &coordinates, player_name, player_color),
+      merkle_proof,
+      recipient,
+      referrer,
+    } => buy_squares_with_allowance(
+      deps,
+      env,
+      info,
+      SquaresOrder {
+        coordinates,
+        shares,
+        player_name,
+        player_color,
+        merkle_proof,
+        recipient,
+        referrer,
+      },
+    ),
+    ExecuteMsg::Contribute {} => contribute(deps, env, info),
+    ExecuteMsg::Receive(wrapper) => receive(deps, env, info, wrapper),
     ExecuteMsg::ChooseWinner { winner } => choose_winner(deps, env, info, winner),
     ExecuteMsg::ClaimRefund {} => claim_refund(deps, env, info),
+    ExecuteMsg::ClaimWinnings {} => claim_winnings(deps, env, info),
+    ExecuteMsg::Sweep {} => sweep(deps, env, info),
+    ExecuteMsg::Finalize {} => finalize(deps, env, info),
   }
 }
 
@@ -58,6 +161,14 @@ pub fn query(
       with_grid,
       with_players,
     } => to_binary(&query_game(deps, with_grid, with_players)?),
+    QueryMsg::Claimable { wallet } => to_binary(&query_claimable(deps, wallet)?),
+    QueryMsg::Referrals { referrer } => to_binary(&query_referrals(deps, referrer)?),
+    QueryMsg::Ledger {} => to_binary(&query_ledger(deps)?),
+    QueryMsg::Players { start_after, limit } => to_binary(&query_players(deps, start_after, limit)?),
+    QueryMsg::Grid { start_after, limit } => to_binary(&query_grid(deps, start_after, limit)?),
+    QueryMsg::Player { wallet } => to_binary(&query_player(deps, wallet)?),
+    QueryMsg::Cell { coords } => to_binary(&query_cell(deps, coords)?),
+    QueryMsg::Contributions { start_after, limit } => to_binary(&query_contributions(deps, start_after, limit)?),
   }?;
   Ok(result)
 }
@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
+use cw_utils::{ParseReplyError, PaymentError};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -9,11 +10,17 @@ pub enum ContractError {
   #[error("NotAuthorized")]
   NotAuthorized {},
 
-  #[error("InsufficientFunds")]
-  InsufficientFunds {},
+  #[error("{0}")]
+  Payment(#[from] PaymentError),
+
+  #[error("{0}")]
+  ParseReply(#[from] ParseReplyError),
 
-  #[error("ExcessFunds")]
-  ExcessFunds {},
+  #[error("InsufficientFunds: expected {expected}, received {received}")]
+  InsufficientFunds { expected: Uint128, received: Uint128 },
+
+  #[error("ExcessFunds: expected {expected}, received {received}")]
+  ExcessFunds { expected: Uint128, received: Uint128 },
 
   #[error("DuplicatePlayerAddress")]
   DuplicatePlayerAddress {},
@@ -54,6 +61,93 @@ pub enum ContractError {
   #[error("CellSoldOut")]
   CellSoldOut {},
 
+  #[error("PlayerHasNfts")]
+  PlayerHasNfts {},
+
   #[error("GameOver")]
   GameOver {},
+
+  #[error("InvalidMerkleRoot")]
+  InvalidMerkleRoot {},
+
+  #[error("InvalidMerkleProof")]
+  InvalidMerkleProof {},
+
+  #[error("InvalidInviteCodeHash")]
+  InvalidInviteCodeHash {},
+
+  #[error("InvalidInviteCode")]
+  InvalidInviteCode {},
+
+  #[error("InviteCodeExpired")]
+  InviteCodeExpired {},
+
+  #[error("InviteCodeExhausted")]
+  InviteCodeExhausted {},
+
+  #[error("InvalidPlayerName")]
+  InvalidPlayerName {},
+
+  #[error("InvalidPlayerColor")]
+  InvalidPlayerColor {},
+
+  #[error("DuplicatePlayerColor")]
+  DuplicatePlayerColor {},
+
+  #[error("InvalidPaymentMethod")]
+  InvalidPaymentMethod {},
+
+  #[error("InvalidCw20Token")]
+  InvalidCw20Token {},
+
+  #[error("NothingToClaim")]
+  NothingToClaim {},
+
+  #[error("InvalidFeeConfig")]
+  InvalidFeeConfig {},
+
+  #[error("InvalidCreatorFee")]
+  InvalidCreatorFee {},
+
+  #[error("InvalidReferralShare")]
+  InvalidReferralShare {},
+
+  #[error("InvalidReferrer")]
+  InvalidReferrer {},
+
+  #[error("SelfReferral")]
+  SelfReferral {},
+
+  #[error("ReferralChain")]
+  ReferralChain {},
+
+  #[error("LedgerImbalance")]
+  LedgerImbalance {},
+
+  #[error("InvalidShareCount")]
+  InvalidShareCount {},
+
+  #[error("InvalidAcceptedDenoms")]
+  InvalidAcceptedDenoms {},
+
+  #[error("InvalidClaimPeriod")]
+  InvalidClaimPeriod {},
+
+  #[error("ClaimDeadlineNotReached")]
+  ClaimDeadlineNotReached {},
+
+  #[error("GameClosed")]
+  GameClosed {},
+
+  #[error("NotSettled")]
+  NotSettled {},
+
+  #[error("AlreadyFinalized")]
+  AlreadyFinalized {},
+
+  #[error("InvalidDigits")]
+  InvalidDigits {},
+
+  #[error("DigitsAlreadyAssigned")]
+  DigitsAlreadyAssigned {},
 }
@@ -0,0 +1,50 @@
+use crate::{
+  error::ContractError,
+  models::InviteCode,
+  state::{require_admin, GAME, INVITE_CODES},
+  util::decode_sha256_hex,
+};
+use cosmwasm_std::{attr, DepsMut, Env, MessageInfo, Response, Timestamp};
+
+pub fn add_invite_code(
+  deps: DepsMut,
+  _env: Env,
+  info: MessageInfo,
+  hash: &str,
+  max_uses: Option<u32>,
+  expires_at: Option<Timestamp>,
+) -> Result<Response, ContractError> {
+  let game = GAME.load(deps.storage)?;
+
+  // address executing this request must be admin
+  require_admin(&game, &info.sender)?;
+
+  // game must be new, not started and not over
+  if game.is_over() {
+    return Err(ContractError::GameOver {});
+  }
+  if game.has_started {
+    return Err(ContractError::AlreadyStarted {});
+  }
+
+  // the admin only ever sends the hash of the invite code, never the code
+  // itself, which must be a hex-encoded SHA-256 hash
+  if decode_sha256_hex(hash).is_none() {
+    return Err(ContractError::InvalidInviteCodeHash {});
+  }
+
+  let hash = hash.to_lowercase();
+
+  // saving an existing hash replaces its use limit and expiry
+  INVITE_CODES.save(
+    deps.storage,
+    hash.clone(),
+    &InviteCode {
+      hash: hash.clone(),
+      uses_remaining: max_uses,
+      expires_at,
+    },
+  )?;
+
+  Ok(Response::new().add_attributes(vec![attr("action", "add_invite_code"), attr("hash", hash)]))
+}
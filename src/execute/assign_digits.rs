@@ -0,0 +1,91 @@
+use crate::{
+  error::ContractError,
+  models::{Digits, Game},
+  state::{require_admin, GAME},
+};
+use cosmwasm_std::{attr, DepsMut, Env, MessageInfo, Response};
+
+pub fn assign_digits(
+  deps: DepsMut,
+  _env: Env,
+  info: MessageInfo,
+  rows: Vec<u8>,
+  cols: Vec<u8>,
+) -> Result<Response, ContractError> {
+  GAME.update(deps.storage, |mut game| -> Result<Game, ContractError> {
+    // address executing this request must be admin
+    require_admin(&game, &info.sender)?;
+    // digits known while squares are still for sale would let players pick
+    // their numbers, so they can only be assigned once sales close
+    if !game.has_started {
+      return Err(ContractError::NotStarted {});
+    }
+    if game.is_over() {
+      return Err(ContractError::GameOver {});
+    }
+    if game.digits.is_some() {
+      return Err(ContractError::DigitsAlreadyAssigned {});
+    }
+    if !is_permutation(&rows) || !is_permutation(&cols) {
+      return Err(ContractError::InvalidDigits {});
+    }
+    game.digits = Some(Digits { rows, cols });
+    Ok(game)
+  })?;
+  Ok(Response::new().add_attributes(vec![attr("action", "assign_digits")]))
+}
+
+/// Returns whether the digits are 0 through 9, each exactly once.
+fn is_permutation(digits: &[u8]) -> bool {
+  let mut sorted = digits.to_vec();
+  sorted.sort_unstable();
+  sorted == (0..10).collect::<Vec<u8>>()
+}
+
+#[cfg(test)]
+mod tests {
+  use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+
+  use crate::{
+    contract::{execute, instantiate},
+    error::ContractError,
+    execute::choose_winner::tests::build_instantiate_msg,
+    msg::ExecuteMsg,
+    query::query_cell::query_cell,
+  };
+
+  #[test]
+  fn digits_are_assigned_only_once_sales_close() {
+    let mut deps = mock_dependencies();
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      build_instantiate_msg(),
+    )
+    .unwrap();
+    let assign = || ExecuteMsg::AssignDigits {
+      rows: vec![3, 1, 4, 0, 5, 9, 2, 6, 8, 7],
+      cols: (0..10).rev().collect(),
+    };
+    let creator = mock_info("creator", &[]);
+
+    let err = execute(deps.as_mut(), mock_env(), creator.clone(), assign()).unwrap_err();
+    assert!(matches!(err, ContractError::NotStarted {}));
+
+    execute(deps.as_mut(), mock_env(), creator.clone(), ExecuteMsg::StartGame {}).unwrap();
+    let invalid = ExecuteMsg::AssignDigits {
+      rows: vec![0, 0, 1, 2, 3, 4, 5, 6, 7, 8],
+      cols: (0..10).collect(),
+    };
+    let err = execute(deps.as_mut(), mock_env(), creator.clone(), invalid).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidDigits {}));
+
+    assert_eq!(query_cell(deps.as_ref(), (2, 0)).unwrap().digits, None);
+    execute(deps.as_mut(), mock_env(), creator.clone(), assign()).unwrap();
+    assert_eq!(query_cell(deps.as_ref(), (2, 0)).unwrap().digits, Some((4, 9)));
+
+    let err = execute(deps.as_mut(), mock_env(), creator, assign()).unwrap_err();
+    assert!(matches!(err, ContractError::DigitsAlreadyAssigned {}));
+  }
+}
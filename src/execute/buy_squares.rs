@@ -1,22 +1,108 @@
 use crate::{
   error::ContractError,
-  models::{Cell, GridCoordinates, Player, Position},
-  state::{GAME, GRID, PLAYERS},
+  models::{Cell, Player, Position, Token},
+  msg::SquaresOrder,
+  nft::mint_cell_token,
+  state::{save_new_player, update_ledger, GAME, GRID, PLAYERS, REFERRALS},
+  treasury::deposit,
+  util::{verify_amount, verify_merkle_proof, verify_native_payment},
 };
-use cosmwasm_std::{attr, Addr, Coin, DepsMut, Env, MessageInfo, Response, Uint128};
-use cw20::Cw20QueryMsg;
+use cosmwasm_std::{attr, to_binary, Addr, DepsMut, Env, MessageInfo, Response, StdResult, Storage, Uint128, WasmMsg};
+use cw20::Cw20ExecuteMsg;
 
+/// Buy squares with the native token sent in the tx's funds. CW20 games must
+/// use the CW20 receive hook or buy_squares_with_allowance instead.
 pub fn buy_squares(
+  mut deps: DepsMut,
+  _env: Env,
+  info: MessageInfo,
+  order: SquaresOrder,
+) -> Result<Response, ContractError> {
+  let game = GAME.load(deps.storage)?;
+  if let Token::Cw20 { .. } = &game.token {
+    return Err(ContractError::InvalidPaymentMethod {});
+  }
+
+  let (player_addr, payment_amount, mint_msgs) = place_order(deps.branch(), &info.sender, order)?;
+
+  // ensure the player is sending the exact funds required for their purchase,
+  // and add each coin to the pot of its denom
+  for coin in verify_native_payment(&info, payment_amount, &game.token)?.iter() {
+    deposit(deps.storage, &game.token, coin)?;
+  }
+
+  Ok(build_response(&info.sender, &player_addr).add_messages(mint_msgs))
+}
+
+/// Buy squares with CW20 tokens sent to this contract through the CW20
+/// receive hook. The amount sent must be exactly the price of the order.
+pub fn buy_squares_with_cw20(
   deps: DepsMut,
   _env: Env,
+  buyer: &Addr,
+  amount: Uint128,
+  order: SquaresOrder,
+) -> Result<Response, ContractError> {
+  let (player_addr, payment_amount, mint_msgs) = place_order(deps, buyer, order)?;
+
+  verify_amount(payment_amount, amount)?;
+
+  Ok(build_response(buyer, &player_addr).add_messages(mint_msgs))
+}
+
+/// Buy squares with CW20 tokens transferred from the sender to this contract,
+/// using the allowance the sender has granted to this contract.
+pub fn buy_squares_with_allowance(
+  deps: DepsMut,
+  env: Env,
   info: MessageInfo,
-  coordinates_list: &Vec<GridCoordinates>,
-  player_name: Option<String>,
-  player_color: Option<String>,
+  order: SquaresOrder,
 ) -> Result<Response, ContractError> {
+  let game = GAME.load(deps.storage)?;
+  let cw20_token_address = match &game.token {
+    Token::Cw20 { address } => address.clone(),
+    _ => return Err(ContractError::InvalidPaymentMethod {}),
+  };
+
+  let (player_addr, payment_amount, mint_msgs) = place_order(deps, &info.sender, order)?;
+
+  // transfer the payment from the sender to this contract. this fails the
+  // whole tx if the sender's allowance or balance is insufficient.
+  let transfer_msg = WasmMsg::Execute {
+    contract_addr: cw20_token_address.into(),
+    msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+      owner: info.sender.clone().into(),
+      recipient: env.contract.address.into(),
+      amount: payment_amount,
+    })?,
+    funds: vec![],
+  };
+
+  Ok(
+    build_response(&info.sender, &player_addr)
+      .add_message(transfer_msg)
+      .add_messages(mint_msgs),
+  )
+}
+
+/// Add the squares in the order to the grid and to the player's positions,
+/// returning the player's address, the total payment amount required and, if
+/// squares are NFTs, msgs minting an NFT for each position.
+fn place_order(
+  deps: DepsMut,
+  buyer: &Addr,
+  order: SquaresOrder,
+) -> Result<(Addr, Uint128, Vec<WasmMsg>), ContractError> {
   let mut game = GAME.load(deps.storage)?;
   let mut payment_amount = Uint128::zero();
-  let player_addr = &info.sender;
+  let mut mint_msgs: Vec<WasmMsg> = vec![];
+
+  // the buyer pays for the squares, but they belong to the recipient, if
+  // given, who must be authorized to play just as if they were the buyer.
+  let player_addr = &match order.recipient {
+    Some(recipient) => deps.api.addr_validate(recipient.as_str())?,
+    None => buyer.clone(),
+  };
 
   if game.is_over() {
     return Err(ContractError::GameOver {});
@@ -26,83 +112,122 @@ pub fn buy_squares(
     return Err(ContractError::NotAuthorized {});
   }
 
-  // if the game is public and this is the first time the wallet has
-  // tried to buy squares, lazy init the player here.
-  if game.is_public {
-    PLAYERS.update(
-      deps.storage,
-      info.sender.clone(),
-      |some_player| -> Result<Player, ContractError> {
-        if let Some(player) = some_player {
-          Ok(player)
-        } else {
-          Ok(Player {
-            wallet: player_addr.clone(),
-            name: player_name.clone(),
-            color: player_color.clone(),
-            positions: None,
-            has_claimed_refund: Some(false),
-          })
-        }
-      },
-    )?;
+  // if this is the first time the wallet has tried to buy squares, lazy init
+  // the player here, provided that the game is public or that the wallet can
+  // prove its inclusion in the game's merkle allowlist. Players registered by
+  // the admin don't need a proof.
+  if !PLAYERS.has(deps.storage, player_addr.clone()) {
+    let is_allowed = if game.is_public {
+      true
+    } else if let (Some(merkle_root), Some(proof)) = (&game.merkle_root, &order.merkle_proof) {
+      verify_merkle_proof(merkle_root, proof, player_addr)?;
+      true
+    } else {
+      false
+    };
+    if is_allowed {
+      save_new_player(
+        deps.storage,
+        player_addr,
+        order.player_name,
+        order.player_color,
+        game.unique_colors,
+      )?;
+    }
   }
 
   if let Some(mut player) = PLAYERS.may_load(deps.storage, player_addr.clone())? {
-    let mut positions = player.positions.unwrap_or(vec![]);
+    let mut positions = player.positions.take().unwrap_or(vec![]);
+    let is_first_order = positions.is_empty();
+
+    // the number of shares to buy in each cell must be given for every cell
+    let n_cells = order.coordinates.len();
+    let share_counts = order.shares.unwrap_or_else(|| vec![1; n_cells]);
+    if share_counts.len() != order.coordinates.len() || share_counts.contains(&0) {
+      return Err(ContractError::InvalidShareCount {});
+    }
 
-    for coords in coordinates_list.iter() {
+    for (coords, n_shares) in order.coordinates.iter().zip(share_counts) {
       // update each puchased cell's state
-      GRID.update(
-        deps.storage,
-        coords.clone(),
-        |some_cell| -> Result<Cell, ContractError> {
-          if let Some(mut cell) = some_cell {
-            let mut player_addrs = cell.wallets.unwrap_or(vec![]);
-            if let Some(max_players_per_cell) = game.max_players_per_cell {
-              if player_addrs.len() == max_players_per_cell as usize {
-                // sold out of spots in this cell
-                return Err(ContractError::CellSoldOut {});
-              }
-            }
-            if player_addrs.contains(player_addr) {
-              // a player can't buy the same cell twice
-              return Err(ContractError::NotAuthorized {});
+      GRID.update(deps.storage, *coords, |some_cell| -> Result<Cell, ContractError> {
+        if let Some(mut cell) = some_cell {
+          // the total number of shares in a cell must fit in a share count
+          if cell.total_shares() + n_shares as u32 > u16::MAX as u32 {
+            return Err(ContractError::InvalidShareCount {});
+          }
+
+          // each share takes up one of the cell's spots
+          if let Some(max_players_per_cell) = game.max_players_per_cell {
+            if cell.total_shares() + n_shares as u32 > max_players_per_cell as u32 {
+              // sold out of spots in this cell
+              return Err(ContractError::CellSoldOut {});
             }
+          }
 
-            // save the player's addr to the cell
-            player_addrs.push(player_addr.clone());
-            cell.wallets = Some(player_addrs);
+          // save the player's addr and shares to the cell
+          cell.add_shares(player_addr, n_shares)?;
 
-            // increment running subtotal for the purchase amount
-            payment_amount += cell.price;
+          // increment running subtotal for the purchase amount
+          payment_amount += cell.price * Uint128::from(n_shares);
 
-            Ok(cell)
-          } else {
-            // invalid grid cell coordinates
-            Err(ContractError::CoordinatesOutOfBounds {})
-          }
-        },
-      )?;
+          Ok(cell)
+        } else {
+          // invalid grid cell coordinates
+          Err(ContractError::CoordinatesOutOfBounds {})
+        }
+      })?;
 
       // add the purchase cell's coordinates and current quarter index
       // to the player's positions.
+      // mint an NFT for the position, if squares are NFTs
+      let token_id = match &game.nft_contract {
+        Some(nft_contract) => {
+          let (token_id, mint_msg) = mint_cell_token(deps.storage, nft_contract, *coords, player_addr, n_shares)?;
+          mint_msgs.push(mint_msg);
+          Some(token_id)
+        },
+        None => None,
+      };
+
       positions.push(Position {
-        coords: coords.clone(),
+        coords: *coords,
         quarter_index: game.quarter_index,
+        shares: Some(n_shares),
+        token_id,
       });
     }
 
-    // ensure the player is sending the exact funds required for their purchase
-    match &game.token {
-      crate::models::Token::Native { denom } => verify_native_funds(&info.funds, payment_amount, &denom)?,
-      crate::models::Token::Cw20 { address } => verify_cw20_funds(&deps, player_addr, payment_amount, &address)?,
+    // a referrer can only be given with the player's first order
+    if let Some(referrer) = &order.referrer {
+      let referrer = deps.api.addr_validate(referrer.as_str())?;
+      if player.referrer.as_ref() != Some(&referrer) {
+        if player.referrer.is_some() || !is_first_order {
+          return Err(ContractError::InvalidReferrer {});
+        }
+        link_referrer(deps.storage, &mut player, buyer, &referrer)?;
+      }
     }
+
+    // record this order's spend for the player's referrer, if any, which earns
+    // a share of the house fee generated by it as quarters are paid out
+    if let Some(referrer) = &player.referrer {
+      REFERRALS.update(deps.storage, referrer.clone(), |some_totals| -> StdResult<_> {
+        let mut totals = some_totals.unwrap_or_default();
+        totals.referred_spend += payment_amount;
+        Ok(totals)
+      })?;
+      game.referred_amount += payment_amount;
+    }
+
     // increment prize pool size with total payment amount for this order
     game.token_amount += payment_amount;
+    GAME.save(deps.storage, &game)?;
 
-    // update the player with their new positions vec
+    update_ledger(deps.storage, |ledger| ledger.collected += payment_amount)?;
+
+    // update the player with their new positions vec and total spend
     player.positions = Some(positions);
+    player.paid = Some(player.paid.unwrap_or_default() + payment_amount);
 
     PLAYERS.save(deps.storage, player_addr.clone(), &player)?;
   } else {
@@ -110,42 +235,48 @@ pub fn buy_squares(
     return Err(ContractError::NotAuthorized {});
   }
 
-  Ok(Response::new().add_attributes(vec![attr("action", "buy_cells")]))
+  Ok((player_addr.clone(), payment_amount, mint_msgs))
 }
 
-// Check for the payment amount required by querying the CW20 token contract.
-fn verify_cw20_funds(
-  deps: &DepsMut,
-  wallet: &Addr,
-  payment_amount: Uint128,
-  cw20_token_address: &Addr,
+/// Link a player to the wallet that referred them, rejecting self-referrals
+/// and referral chains. A referred player can't refer others, and a referrer
+/// can't be referred.
+fn link_referrer(
+  storage: &mut dyn Storage,
+  player: &mut Player,
+  buyer: &Addr,
+  referrer: &Addr,
 ) -> Result<(), ContractError> {
-  let resp: cw20::BalanceResponse = deps.querier.query_wasm_smart(
-    cw20_token_address.clone(),
-    &Cw20QueryMsg::Balance {
-      address: wallet.clone().into(),
-    },
-  )?;
-  if resp.balance < payment_amount {
-    return Err(ContractError::InsufficientFunds {});
+  if *referrer == player.wallet || referrer == buyer {
+    return Err(ContractError::SelfReferral {});
   }
-  Ok(())
-}
-
-// Check for the exact payment amount required in the tx's funds.
-fn verify_native_funds(
-  funds: &Vec<Coin>,
-  payment_amount: Uint128,
-  denom: &String,
-) -> Result<(), ContractError> {
-  if let Some(coin) = funds.iter().find(|coin| -> bool { coin.denom == *denom }) {
-    if coin.amount < payment_amount {
-      return Err(ContractError::InsufficientFunds {});
-    } else if coin.amount > payment_amount {
-      return Err(ContractError::ExcessFunds {});
+  if REFERRALS.has(storage, player.wallet.clone()) {
+    return Err(ContractError::ReferralChain {});
+  }
+  if let Some(referrer_player) = PLAYERS.may_load(storage, referrer.clone())? {
+    if referrer_player.referrer.is_some() {
+      return Err(ContractError::ReferralChain {});
     }
-  } else {
-    return Err(ContractError::InsufficientFunds {});
   }
+
+  player.referrer = Some(referrer.clone());
+
+  REFERRALS.update(storage, referrer.clone(), |some_totals| -> StdResult<_> {
+    let mut totals = some_totals.unwrap_or_default();
+    totals.referred_players += 1;
+    Ok(totals)
+  })?;
+
   Ok(())
 }
+
+fn build_response(
+  buyer: &Addr,
+  player_addr: &Addr,
+) -> Response {
+  Response::new().add_attributes(vec![
+    attr("action", "buy_cells"),
+    attr("buyer", buyer.to_string()),
+    attr("player", player_addr.to_string()),
+  ])
+}
@@ -1,24 +1,16 @@
 use crate::{
   error::ContractError,
-  models::{GridCoordinates, Token},
-  state::{require_admin, GAME, GRID},
-  util::{build_cw20_transfer_msg, build_native_send_msg, compute_amount_from_pct},
+  models::{FeeConfig, GridCoordinates, UnsoldSeedPolicy},
+  nft::load_cell_owners,
+  payout::{compute_quarter_payout, compute_quarter_share, compute_seed_portion, create_refund_pool, split_by_weights},
+  state::{load_fee_config, require_admin, update_ledger, CLAIMABLE, GAME, GRID},
+  treasury::Treasury,
 };
-use cosmwasm_std::{attr, Addr, CosmosMsg, DepsMut, Env, MessageInfo, Response, SubMsg, Uint128};
-
-// addresses for base gelotto tax:
-pub const GELOTTO_ADDR: &str = "juno1jume25ttjlcaqqjzjjqx9humvze3vcc8z87szj";
-pub const GELOTTO_ANNUAL_GRAND_PRIZE_ADDR: &str = "juno1fxu5as8z5qxdulujzph3rm6c39r8427mjnx99r";
-pub const GELOTTO_NFT_1_REWARDS_ADDR: &str = "juno1tlyqv2ss4p9zelllxm39hq5g6zw384mvvym6tp";
-
-// percentage split of the 10% base gelotto tax:
-pub const GELOTTO_PCT: u8 = 20;
-pub const GELOTTO_ANNUAL_GRAND_PRIZE_PCT: u8 = 50;
-pub const GELOTTO_NFT_1_REWARDS_PCT: u8 = 30;
+use cosmwasm_std::{attr, Addr, DepsMut, Env, MessageInfo, Response, StdResult, SubMsg, Uint128};
 
 pub fn choose_winner(
   deps: DepsMut,
-  _env: Env,
+  env: Env,
   info: MessageInfo,
   winner: GridCoordinates,
 ) -> Result<Response, ContractError> {
@@ -36,79 +28,139 @@ pub fn choose_winner(
   }
 
   let n_quarters: usize = game.quarters.len();
-  let quarter = &mut game.quarters[game.quarter_index as usize];
+  let quarter_index = game.quarter_index as usize;
 
   // can't resolve the same quarter winner twice
-  if quarter.winner != None {
+  if game.quarters[quarter_index].winner.is_some() {
     return Err(ContractError::AlreadyResolved {});
   }
 
   // save the winning coordinates for the resolving quarter
-  quarter.winner = Some(winner.clone());
+  game.quarters[quarter_index].winner = Some(winner);
 
-  // calculate prize amount for the winning wallets, distributed evenly to each
-  // player in the winning grid cell.
-  let full_quarter_prize_amount = compute_amount_from_pct(game.token_amount, quarter.pct);
+  // storage for transfer msgs to winners, fee recipients and creator:
+  let mut transfer_msgs: Vec<SubMsg> = vec![];
+  let mut treasury = Treasury::load(deps.storage, &game)?;
 
-  // subtract 10%, as 10% is Gelotto's tax
-  let quarter_prize_amount = compute_amount_from_pct(full_quarter_prize_amount, 90);
-
-  // storage for transfer msgs to winners:
-  let mut transfer_msgs: Vec<CosmosMsg> = vec![];
-  let mut cw20_transfer_msgs: Vec<SubMsg> = vec![];
+  // amounts to record in the ledger
+  let mut paid_amount = Uint128::zero();
+  let mut taxed_amount = Uint128::zero();
+  let mut refunded_amount = Uint128::zero();
+  let mut outstanding_amount = Uint128::zero();
 
   // init response
   let mut resp = Response::new().add_attributes(vec![attr("action", "resolve_winner")]);
 
   if let Some(winning_cell) = GRID.may_load(deps.storage, winner)? {
-    // get addrs of players in grid cell that won
-    let winning_wallets = winning_cell.wallets.unwrap_or(vec![]);
+    // get addrs of players in grid cell that won, and their shares. if squares
+    // are NFTs, the shares are owed to the NFTs' current owners.
+    let owners = load_cell_owners(deps.as_ref(), &game, winner, &winning_cell)?;
+    let winning_shares: Vec<u128> = owners.iter().map(|(_, shares)| shares.u128()).collect();
+    let winning_wallets: Vec<Addr> = owners.into_iter().map(|(addr, _)| addr).collect();
+    game.quarters[quarter_index].winners = Some(winning_wallets.clone());
     // if there are any....
-    if winning_wallets.len() > 0 {
-      // calc the amount owed to each winning player address
-      let player_prize_pct = winning_wallets.len() as u8 / 100;
-      let player_prize_amount = compute_amount_from_pct(quarter_prize_amount, player_prize_pct);
-
-      // create a transfer message from the contract to winner address
-      for addr in winning_wallets.iter() {
-        match &game.token {
-          Token::Native { denom } => transfer_msgs.push(build_native_send_msg(addr, denom, player_prize_amount)?),
-          Token::Cw20 { address: cw20_addr } => {
-            cw20_transfer_msgs.push(build_cw20_transfer_msg(&addr, &cw20_addr, player_prize_amount)?)
-          },
+    if !winning_wallets.is_empty() {
+      // split this quarter's share of the pot into the house fee and the
+      // prizes owed to each winning player address, in proportion to shares.
+      let quarter_share = compute_quarter_share(&game);
+      let (fees, is_external_fee_config) = load_fee_config(deps.as_ref(), &game);
+      let creator_fee_bps = game.creator_fee.as_ref().map(|c| c.rate_bps).unwrap_or(0);
+      let payout = compute_quarter_payout(quarter_share, &winning_shares, &fees, creator_fee_bps, &game);
+
+      // referral rewards withheld from the house fee stay in the contract
+      // until referrers claim them at the end of the game
+      game.referral_balance += payout.referral_amount;
+      outstanding_amount += payout.referral_amount;
+
+      for (addr, prize_amount) in winning_wallets.iter().zip(payout.prizes) {
+        if prize_amount.is_zero() {
+          continue;
+        }
+        if game.push_payouts {
+          // create a transfer message from the contract to winner address
+          transfer_msgs.extend(treasury.build_transfer_msg(addr, prize_amount)?);
+          paid_amount += prize_amount;
+        } else {
+          // record the amount owed to the winner, who claims it later
+          CLAIMABLE.update(deps.storage, addr.clone(), |some_amount| -> StdResult<Uint128> {
+            Ok(some_amount.unwrap_or_default() + prize_amount)
+          })?;
+          outstanding_amount += prize_amount;
         }
       }
 
-      // add transfer msgs required by gelotto tax
-      let gelotto_tax_amount = full_quarter_prize_amount - quarter_prize_amount;
-      resp = append_gelotto_tax_msgs(&resp, &game.token, gelotto_tax_amount)?;
-    } else if (game.quarter_index as usize) == (n_quarters - 1) {
-      // There aren't any buyers for the winning square AND it's the last round.
-      // In this case, put the contract in a state where each player can claim
-      // a refund for their remaining balance.
-      game.can_claim_refund = true;
+      // add transfer msgs required by the house fee
+      transfer_msgs.append(&mut build_fee_msgs(&mut treasury, &fees, &payout.fees)?);
+      taxed_amount += payout.fees.iter().sum::<Uint128>();
+
+      // add transfer msg for the creator fee, if any
+      if let Some(creator_fee) = &game.creator_fee {
+        if !payout.creator_fee.is_zero() {
+          transfer_msgs.extend(treasury.build_transfer_msg(&creator_fee.recipient, payout.creator_fee)?);
+          taxed_amount += payout.creator_fee;
+        }
+      }
+
+      // the quarter's entire share, including its part of the seed pool, has
+      // now been paid out or is claimable
+      game.seed_balance -= compute_seed_portion(&game, quarter_share);
+      game.distributed_amount += quarter_share;
+
+      resp = resp.add_attributes(vec![
+        attr("quarter_amount", quarter_share.to_string()),
+        attr("external_fee_config", is_external_fee_config.to_string()),
+      ]);
     } else {
-      // If we're here, then the winning square has no buyers. In this case, we
-      // zero out the ending quarter's percent, and distribute it
-      // proportionally to the remaining quarters.
-      let ending_quarter_pct = quarter.pct;
-      quarter.pct = 0;
-
-      let remaining_quarters = &game.quarters.clone()[((game.quarter_index + 1) as usize)..];
-      let total_future_pct: u8 = remaining_quarters.iter().map(|q| q.pct).sum();
-
-      // increment the pct value of remaining quarters
-      for (i, future_quarter) in remaining_quarters.iter().enumerate() {
-        let ratio = future_quarter.pct / total_future_pct;
-        let new_pct = future_quarter.pct + (ending_quarter_pct * ratio);
-        let future_quarter_index = i + game.quarter_index as usize;
-        game.quarters[future_quarter_index].pct = new_pct;
+      // no one bought the winning square, so this quarter's part of the seed
+      // pool is handled according to the game's unsold seed policy. after the
+      // final quarter, there's nothing left to roll the seed over into.
+      let is_final_quarter = quarter_index == n_quarters - 1;
+      let seed_portion = if is_final_quarter {
+        game.seed_balance
+      } else {
+        compute_seed_portion(&game, compute_quarter_share(&game))
+      };
+      let is_seed_rolled_over = game.unsold_seed_policy == UnsoldSeedPolicy::Rollover && !is_final_quarter;
+      if !(seed_portion.is_zero() || is_seed_rolled_over) {
+        let (fees, _) = load_fee_config(deps.as_ref(), &game);
+        if game.unsold_seed_policy == UnsoldSeedPolicy::PayHouse && !fees.recipients.is_empty() {
+          let fee_weights: Vec<u128> = fees.recipients.iter().map(|r| r.weight as u128).collect();
+          let seed_amounts = split_by_weights(seed_portion, &fee_weights);
+          transfer_msgs.append(&mut build_fee_msgs(&mut treasury, &fees, &seed_amounts)?);
+          taxed_amount += seed_portion;
+        } else {
+          transfer_msgs.extend(treasury.build_transfer_msg(&game.admin, seed_portion)?);
+          refunded_amount += seed_portion;
+        }
+        game.seed_balance -= seed_portion;
+        game.distributed_amount += seed_portion;
+        resp = resp.add_attribute("seed_amount", seed_portion.to_string());
       }
-      // if, due to integer arithmetic, the new total percent is less than 100,
-      // add the difference, which should always only be 0 or 1, to the final quarter.
-      let new_total_pct: u8 = game.quarters.iter().map(|q| q.pct).sum();
-      if new_total_pct < 100 {
-        game.quarters[n_quarters - 1].pct += 100 - new_total_pct;
+
+      if is_final_quarter {
+        // There aren't any buyers for the winning square AND it's the last round.
+        // In this case, put the contract in a state where each player can claim
+        // a refund for their share of the pot's remaining balance.
+        game.can_claim_refund = true;
+        game.refund_pool = Some(create_refund_pool(&game));
+      } else {
+        // If we're here, then the winning square has no buyers. In this case, we
+        // zero out the ending quarter's basis points, and distribute them
+        // proportionally to the remaining quarters. The undistributed balance of
+        // the pot is carried over to the remaining quarters along with it.
+        let ending_quarter_bps = game.quarters[quarter_index].bps;
+        game.quarters[quarter_index].bps = 0;
+
+        let future_bps: Vec<u128> = game.quarters[(quarter_index + 1)..]
+          .iter()
+          .map(|q| q.bps as u128)
+          .collect();
+        let bps_increments = split_by_weights(Uint128::from(ending_quarter_bps), &future_bps);
+
+        // increment the bps value of remaining quarters
+        for (i, bps_increment) in bps_increments.iter().enumerate() {
+          game.quarters[quarter_index + 1 + i].bps += bps_increment.u128() as u16;
+        }
       }
     }
   } else {
@@ -120,50 +172,267 @@ pub fn choose_winner(
   // quarter or, in the final case, ending the game
   game.quarter_index += 1;
 
+  // once the game is over, winnings and refunds can be claimed until the
+  // claim deadline, if any, after which they can be swept to the treasury
+  if game.is_over() {
+    if let Some(claim_period) = game.claim_period {
+      game.claim_deadline = Some(env.block.time.plus_seconds(claim_period));
+    }
+  }
+
   // save all changes to quarters through the game
   GAME.save(deps.storage, &game)?;
+  treasury.save(deps.storage)?;
+
+  update_ledger(deps.storage, |ledger| {
+    ledger.paid += paid_amount;
+    ledger.taxed += taxed_amount;
+    ledger.refunded += refunded_amount;
+    ledger.outstanding += outstanding_amount;
+  })?;
 
   // send response with transfer msgs to winning wallets
-  Ok(resp.add_submessages(cw20_transfer_msgs).add_messages(transfer_msgs))
+  Ok(resp.add_submessages(transfer_msgs))
 }
 
-/// add transfer msgs to response for Gelotto's tax
-fn append_gelotto_tax_msgs(
-  resp: &Response,
-  token: &Token,
-  total_tax: Uint128,
-) -> Result<Response, ContractError> {
-  let mut params: Vec<(&str, u8)> = Vec::with_capacity(3);
-  params.push((GELOTTO_ADDR, GELOTTO_PCT));
-  params.push((GELOTTO_ANNUAL_GRAND_PRIZE_ADDR, GELOTTO_ANNUAL_GRAND_PRIZE_PCT));
-  params.push((GELOTTO_NFT_1_REWARDS_ADDR, GELOTTO_NFT_1_REWARDS_PCT));
-
-  match &token {
-    Token::Native { denom } => {
-      let mut msgs: Vec<CosmosMsg> = Vec::with_capacity(3);
-      for (to_addr_str, pct) in params.iter() {
-        let amount: Uint128 = compute_amount_from_pct(total_tax, *pct);
-        msgs.push(build_native_send_msg(
-          &Addr::unchecked(to_addr_str.to_string()),
-          &denom,
-          amount,
-        )?);
-      }
-      Ok(resp.clone().add_messages(msgs))
-    },
-    Token::Cw20 {
-      address: cw20_token_addr,
-    } => {
-      let mut msgs: Vec<SubMsg> = Vec::with_capacity(3);
-      for (to_addr_str, pct) in params.iter() {
-        let amount: Uint128 = compute_amount_from_pct(total_tax, *pct);
-        msgs.push(build_cw20_transfer_msg(
-          &Addr::unchecked(to_addr_str.to_string()),
-          &cw20_token_addr,
-          amount,
-        )?);
-      }
-      Ok(resp.clone().add_submessages(msgs))
-    },
+/// build transfer msgs for the house fee, given the amount owed to each fee
+/// recipient
+fn build_fee_msgs(
+  treasury: &mut Treasury,
+  fees: &FeeConfig,
+  fee_amounts: &[Uint128],
+) -> Result<Vec<SubMsg>, ContractError> {
+  let mut msgs: Vec<SubMsg> = Vec::with_capacity(fees.recipients.len());
+  for (recipient, amount) in fees.recipients.iter().zip(fee_amounts.iter()) {
+    msgs.extend(treasury.build_transfer_msg(&recipient.address, *amount)?);
+  }
+  Ok(msgs)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use cosmwasm_std::{
+    coins,
+    testing::{mock_dependencies, mock_env, mock_info},
+    Addr, Uint128,
+  };
+
+  use crate::{
+    contract::{execute, instantiate},
+    error::ContractError,
+    models::{Cell, FeeConfig, FeeRecipient, Quarter, Team, Token},
+    msg::{ExecuteMsg, InstantiateMsg},
+    state::{CLAIMABLE, GAME, LEDGER},
+  };
+
+  pub(crate) fn build_instantiate_msg() -> InstantiateMsg {
+    InstantiateMsg {
+      name: "game".to_owned(),
+      teams: vec![
+        Team {
+          name: "home".to_owned(),
+          color: "#ff0000".to_owned(),
+        },
+        Team {
+          name: "away".to_owned(),
+          color: "#0000ff".to_owned(),
+        },
+      ],
+      is_public: true,
+      players: None,
+      max_players_per_cell: None,
+      quarters: (0..4)
+        .map(|_| Quarter {
+          winner: None,
+          winners: None,
+          name: None,
+          bps: 2500,
+          pct: None,
+        })
+        .collect(),
+      grid: (0..100)
+        .map(|_| Cell {
+          wallets: None,
+          price: Uint128::new(3),
+          shares: None,
+        })
+        .collect(),
+      token: Token::Native {
+        denom: "ujuno".to_owned(),
+      },
+      merkle_root: None,
+      unique_colors: None,
+      push_payouts: None,
+      fees: FeeConfig {
+        rate_bps: 5000,
+        recipients: vec![FeeRecipient {
+          address: Addr::unchecked("house"),
+          weight: 1,
+        }],
+      },
+      fee_config_contract: None,
+      creator_fee_bps: None,
+      creator_fee_recipient: None,
+      referral_bps: Some(10000),
+      sponsor_refund_policy: None,
+      seed_amount: None,
+      unsold_seed_policy: None,
+      nft: None,
+      claim_period: None,
+      treasury: None,
+    }
+  }
+
+  fn buy_squares(
+    deps: cosmwasm_std::DepsMut,
+    coordinates: Vec<(u8, u8)>,
+  ) {
+    let payment = coins(3 * coordinates.len() as u128, "ujuno");
+    let msg = ExecuteMsg::BuySquares {
+      coordinates,
+      shares: None,
+      player_name: None,
+      player_color: None,
+      merkle_proof: None,
+      recipient: None,
+      referrer: Some(Addr::unchecked("referrer")),
+    };
+    execute(deps, mock_env(), mock_info("buyer", &payment), msg).unwrap();
+  }
+
+  fn choose_winner(
+    deps: cosmwasm_std::DepsMut,
+    winner: (u8, u8),
+  ) -> Result<(), ContractError> {
+    execute(
+      deps,
+      mock_env(),
+      mock_info("creator", &[]),
+      ExecuteMsg::ChooseWinner { winner },
+    )
+    .map(|_| ())
+  }
+
+  #[test]
+  fn referral_rewards_never_lock_up_the_game() {
+    let mut deps = mock_dependencies();
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      build_instantiate_msg(),
+    )
+    .unwrap();
+    buy_squares(deps.as_mut(), vec![(0, 0)]);
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      ExecuteMsg::StartGame {},
+    )
+    .unwrap();
+
+    // the house fee taken from each quarter rounds down to nothing, so no
+    // referral rewards are earned
+    for _ in 0..4 {
+      choose_winner(deps.as_mut(), (0, 0)).unwrap();
+    }
+
+    let game = GAME.load(&deps.storage).unwrap();
+    let ledger = LEDGER.load(&deps.storage).unwrap();
+    assert!(game.referral_balance.is_zero());
+    assert_eq!(ledger.unallocated(), Some(Uint128::zero()));
+    assert_eq!(ledger.outstanding, Uint128::new(3));
+
+    // the referrer has nothing to claim, since no fee was charged
+    let result = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("referrer", &[]),
+      ExecuteMsg::ClaimWinnings {},
+    );
+    assert!(matches!(result, Err(ContractError::NothingToClaim {})));
+  }
+
+  #[test]
+  fn refundable_games_pay_no_referral_rewards() {
+    let mut deps = mock_dependencies();
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      build_instantiate_msg(),
+    )
+    .unwrap();
+    buy_squares(deps.as_mut(), vec![(0, 0)]);
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      ExecuteMsg::StartGame {},
+    )
+    .unwrap();
+
+    // no one bought the winning square of any quarter
+    for _ in 0..4 {
+      choose_winner(deps.as_mut(), (9, 9)).unwrap();
+    }
+
+    // the buyer gets back everything they paid
+    let game = GAME.load(&deps.storage).unwrap();
+    assert!(game.can_claim_refund);
+    assert!(game.referral_balance.is_zero());
+    assert_eq!(game.refund_pool.unwrap().amount, Uint128::new(3));
+    let ledger = LEDGER.load(&deps.storage).unwrap();
+    assert!(ledger.outstanding.is_zero());
+  }
+
+  #[test]
+  fn referrers_get_their_share_of_the_fees_charged() {
+    let mut deps = mock_dependencies();
+    instantiate(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      build_instantiate_msg(),
+    )
+    .unwrap();
+    buy_squares(deps.as_mut(), (0..10).map(|col| (0, col)).collect());
+    execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("creator", &[]),
+      ExecuteMsg::StartGame {},
+    )
+    .unwrap();
+
+    // the pot of 30 is split into quarters of 8, 8, 7 and 7, which are charged
+    // fees of 4, 4, 3 and 3. every player was referred, and referrers get the
+    // whole fee they generated, so winners keep the rest of each quarter.
+    for _ in 0..4 {
+      choose_winner(deps.as_mut(), (0, 0)).unwrap();
+    }
+
+    let game = GAME.load(&deps.storage).unwrap();
+    assert_eq!(game.referral_balance, Uint128::new(14));
+    assert_eq!(
+      CLAIMABLE.load(&deps.storage, Addr::unchecked("buyer")).unwrap(),
+      Uint128::new(16)
+    );
+
+    let resp = execute(
+      deps.as_mut(),
+      mock_env(),
+      mock_info("referrer", &[]),
+      ExecuteMsg::ClaimWinnings {},
+    )
+    .unwrap();
+    assert!(resp
+      .attributes
+      .iter()
+      .any(|a| a.key == "referral_amount" && a.value == "14"));
+    let ledger = LEDGER.load(&deps.storage).unwrap();
+    assert_eq!(ledger.outstanding, Uint128::new(16));
+    assert_eq!(ledger.unallocated(), Some(Uint128::zero()));
   }
 }
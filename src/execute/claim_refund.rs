@@ -1,7 +1,8 @@
 use crate::{
   error::ContractError,
-  state::{GAME, GRID, PLAYERS},
-  util::{build_cw20_transfer_msg, build_native_send_msg, compute_amount_from_pct},
+  payout::compute_refund,
+  state::{update_ledger, CONTRIBUTIONS, GAME, PLAYERS},
+  treasury::Treasury,
 };
 use cosmwasm_std::{attr, DepsMut, Env, MessageInfo, Response, Uint128};
 
@@ -10,55 +11,85 @@ pub fn claim_refund(
   _env: Env,
   info: MessageInfo,
 ) -> Result<Response, ContractError> {
-  let game = GAME.load(deps.storage)?;
+  let mut game = GAME.load(deps.storage)?;
+
+  // unclaimed funds have been swept to the treasury
+  if game.is_closed {
+    return Err(ContractError::GameClosed {});
+  }
 
   // game must be over and refundable
   if !(game.is_over() && game.can_claim_refund) {
     return Err(ContractError::NotAuthorized {});
   }
+  let mut pool = game.refund_pool.clone().ok_or(ContractError::NotAuthorized {})?;
 
-  let mut resp = Response::new().add_attributes(vec![attr("action", "claim_refund")]);
+  // the claimant's weight in the refund pool is the total amount they paid
+  // into the pot, as a player and as a sponsor, if sponsors are refunded.
+  let mut weight = Uint128::zero();
+  let mut is_claimant = false;
+  let mut n_claims = 0;
 
-  // only existing players can claim refund
+  // refund existing players
   if let Some(mut player) = PLAYERS.may_load(deps.storage, info.sender.clone())? {
-    if player.has_claimed_refund.unwrap_or(false) {
-      return Err(ContractError::AlreadyClaimedRefund {});
+    is_claimant = true;
+    if !player.has_claimed_refund.unwrap_or(false) {
+      weight += player.paid.unwrap_or_default();
+
+      // flag the player as refunded to prevent double-claims
+      player.has_claimed_refund = Some(true);
+      PLAYERS.save(deps.storage, info.sender.clone(), &player)?;
+      n_claims += 1;
     }
-    if let Some(positions) = &player.positions {
-      // tabulate total amount spent by player
-      let mut total_spend = Uint128::zero();
-      for p in positions.iter() {
-        let cell = GRID.load(deps.storage, p.coords.clone())?;
-        total_spend += cell.price;
-      }
+  }
 
-      // compute refund amount
-      let final_quarter = &game.quarters[game.quarters.len() - 1];
-      let refund_amount = compute_amount_from_pct(total_spend, final_quarter.pct);
+  // refund sponsors
+  if pool.includes_sponsors {
+    if let Some(mut contribution) = CONTRIBUTIONS.may_load(deps.storage, info.sender.clone())? {
+      is_claimant = true;
+      if !contribution.has_claimed_refund {
+        weight += contribution.amount;
 
-      // add refund transfer msg to response
-      resp = match &game.token {
-        crate::models::Token::Native { denom } => {
-          resp.add_message(build_native_send_msg(&info.sender, &denom, refund_amount)?)
-        },
-        crate::models::Token::Cw20 {
-          address: cw20_token_address,
-        } => resp.add_submessage(build_cw20_transfer_msg(
-          &info.sender,
-          &cw20_token_address,
-          refund_amount,
-        )?),
+        // flag the sponsor as refunded to prevent double-claims
+        contribution.has_claimed_refund = true;
+        CONTRIBUTIONS.save(deps.storage, info.sender.clone(), &contribution)?;
+        n_claims += 1;
       }
     }
+  }
 
-    // flag the player as refunded to prevent double-claims
-    player.has_claimed_refund = Some(true);
-
-    PLAYERS.save(deps.storage, info.sender.clone(), &player)?;
+  if !is_claimant {
+    // claimant is not a registered player or a sponsor
+    return Err(ContractError::NotAuthorized {});
+  }
+  if n_claims == 0 {
+    return Err(ContractError::AlreadyClaimedRefund {});
+  }
 
-    Ok(resp)
+  // compute the claimant's pro-rata share of the refund pool
+  let refund_amount = if weight.is_zero() {
+    Uint128::zero()
   } else {
-    // claimant is not a registered player
-    Err(ContractError::NotAuthorized {})
-  }
+    compute_refund(&pool, weight)
+  };
+  pool.claimed_weight += weight;
+  pool.claimed_amount += refund_amount;
+  game.refund_pool = Some(pool);
+  GAME.save(deps.storage, &game)?;
+
+  // build refund transfer msg, if there's anything to refund
+  let mut treasury = Treasury::load(deps.storage, &game)?;
+  let transfer_msg = treasury.build_transfer_msg(&info.sender, refund_amount)?;
+  treasury.save(deps.storage)?;
+
+  update_ledger(deps.storage, |ledger| ledger.refunded += refund_amount)?;
+
+  Ok(
+    Response::new()
+      .add_attributes(vec![
+        attr("action", "claim_refund"),
+        attr("amount", refund_amount.to_string()),
+      ])
+      .add_submessages(transfer_msg),
+  )
 }
@@ -0,0 +1,66 @@
+use crate::{
+  error::ContractError,
+  state::{load_referral_payout, update_ledger, CLAIMABLE, GAME, REFERRALS},
+  treasury::Treasury,
+};
+use cosmwasm_std::{attr, DepsMut, Env, MessageInfo, Response, Uint128};
+
+pub fn claim_winnings(
+  deps: DepsMut,
+  _env: Env,
+  info: MessageInfo,
+) -> Result<Response, ContractError> {
+  let mut game = GAME.load(deps.storage)?;
+
+  // unclaimed funds have been swept to the treasury
+  if game.is_closed {
+    return Err(ContractError::GameClosed {});
+  }
+
+  if !game.has_started {
+    return Err(ContractError::NotStarted {});
+  }
+
+  let mut treasury = Treasury::load(deps.storage, &game)?;
+  let prize_amount = CLAIMABLE
+    .may_load(deps.storage, info.sender.clone())?
+    .unwrap_or_default();
+
+  // referral rewards are paid out of the amount withheld for them, once the
+  // game is over
+  let mut referral_amount = Uint128::zero();
+  if let Some((mut totals, amount)) = load_referral_payout(deps.storage, &game, &info.sender)? {
+    game.referred_amount -= totals.referred_spend;
+    game.referral_balance -= amount;
+    totals.claimed_rewards = Some(amount);
+    REFERRALS.save(deps.storage, info.sender.clone(), &totals)?;
+    GAME.save(deps.storage, &game)?;
+    referral_amount = amount;
+  }
+
+  let amount = prize_amount + referral_amount;
+  if amount.is_zero() {
+    return Err(ContractError::NothingToClaim {});
+  }
+
+  // clear the claimable balance to prevent double-claims
+  CLAIMABLE.remove(deps.storage, info.sender.clone());
+
+  let transfer_msg = treasury.build_transfer_msg(&info.sender, amount)?;
+  treasury.save(deps.storage)?;
+
+  update_ledger(deps.storage, |ledger| {
+    ledger.outstanding -= amount;
+    ledger.paid += amount;
+  })?;
+
+  Ok(
+    Response::new()
+      .add_attributes(vec![
+        attr("action", "claim_winnings"),
+        attr("amount", amount.to_string()),
+        attr("referral_amount", referral_amount.to_string()),
+      ])
+      .add_submessages(transfer_msg),
+  )
+}
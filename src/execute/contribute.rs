@@ -0,0 +1,83 @@
+use crate::{
+  error::ContractError,
+  models::{Contribution, Token},
+  state::{update_ledger, CONTRIBUTIONS, GAME},
+  treasury::deposit,
+  util::{compute_base_amount, find_accepted_coins},
+};
+use cosmwasm_std::{attr, Addr, DepsMut, Env, MessageInfo, Response, StdResult, Uint128};
+use cw_utils::{must_pay, PaymentError};
+
+/// Contribute native tokens sent in the tx's funds to the prize pool.
+pub fn contribute(
+  deps: DepsMut,
+  _env: Env,
+  info: MessageInfo,
+) -> Result<Response, ContractError> {
+  let game = GAME.load(deps.storage)?;
+  let amount = match &game.token {
+    Token::Native { denom } => must_pay(&info, denom)?,
+    Token::Natives { denoms } => {
+      // contributions in any accepted denoms are recorded in base units
+      let mut amount = Uint128::zero();
+      for (coin, accepted_denom) in find_accepted_coins(&info, denoms)?.iter() {
+        deposit(deps.storage, &game.token, coin)?;
+        amount += compute_base_amount(coin.amount, accepted_denom.rate);
+      }
+      amount
+    },
+    Token::Cw20 { .. } => return Err(ContractError::InvalidPaymentMethod {}),
+  };
+  record_contribution(deps, &info.sender, amount)
+}
+
+/// Contribute CW20 tokens sent to this contract through the CW20 receive hook
+/// to the prize pool.
+pub fn contribute_cw20(
+  deps: DepsMut,
+  _env: Env,
+  sponsor: &Addr,
+  amount: Uint128,
+) -> Result<Response, ContractError> {
+  record_contribution(deps, sponsor, amount)
+}
+
+fn record_contribution(
+  deps: DepsMut,
+  sponsor: &Addr,
+  amount: Uint128,
+) -> Result<Response, ContractError> {
+  let mut game = GAME.load(deps.storage)?;
+
+  // contributions are accepted until the game is over. contributions made
+  // after the game has started are split between the remaining quarters.
+  if game.is_over() {
+    return Err(ContractError::GameOver {});
+  }
+  if amount.is_zero() {
+    return Err(PaymentError::NoFunds {}.into());
+  }
+
+  CONTRIBUTIONS.update(deps.storage, sponsor.clone(), |some_contribution| -> StdResult<_> {
+    let mut contribution = some_contribution.unwrap_or(Contribution {
+      sponsor: sponsor.clone(),
+      amount: Uint128::zero(),
+      has_claimed_refund: false,
+    });
+    contribution.amount += amount;
+    Ok(contribution)
+  })?;
+
+  // increase the prize pool without creating any positions
+  game.token_amount += amount;
+  game.sponsor_amount += amount;
+  GAME.save(deps.storage, &game)?;
+
+  update_ledger(deps.storage, |ledger| ledger.collected += amount)?;
+
+  Ok(Response::new().add_attributes(vec![
+    attr("action", "contribute"),
+    attr("sponsor", sponsor.to_string()),
+    attr("amount", amount.to_string()),
+  ]))
+}
@@ -0,0 +1,204 @@
+use crate::{
+  error::ContractError,
+  models::{Game, GameSummary, QuarterSummary, Token},
+  payout::split_by_weights,
+  state::{
+    load_fee_config, require_admin, update_ledger, CELL_TOKENS, CLAIMABLE, CONTRIBUTIONS, GAME, GRID, INVITE_CODES,
+    LEDGER, PLAYERS, PLAYER_COLORS, POTS, REFERRALS,
+  },
+  util::build_cw20_transfer_msg,
+};
+use cosmwasm_std::{
+  attr, Addr, BankMsg, Coin, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult, Storage, SubMsg, Uint128,
+};
+use cw20::{BalanceResponse, Cw20QueryMsg};
+use cw_storage_plus::{KeyDeserialize, Map, PrimaryKey};
+use serde::{de::DeserializeOwned, Serialize};
+
+// max number of storage entries removed by each finalize tx
+pub const FINALIZE_BATCH_SIZE: usize = 100;
+
+/// Finalize a settled game. The first call sends any dust left in the
+/// contract's balance to the house fee recipients and records a summary of
+/// the game. Each call, including the first, removes a batch of per-player
+/// and per-cell state, so finalize is executed until it reports that the
+/// cleanup is done.
+pub fn finalize(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+) -> Result<Response, ContractError> {
+  let mut game = GAME.load(deps.storage)?;
+
+  require_admin(&game, &info.sender)?;
+
+  let mut resp = Response::new().add_attributes(vec![attr("action", "finalize")]);
+
+  if game.summary.is_none() {
+    // the game is settled once it's over and nothing is left to claim
+    if !is_settled(deps.storage, &game)? {
+      return Err(ContractError::NotSettled {});
+    }
+
+    // send the dust left in the contract's balance to the fee recipients
+    let dust = query_dust(deps.as_ref(), &env, &game)?;
+    let (fees, _) = load_fee_config(deps.as_ref(), &game);
+    let mut recipients: Vec<Addr> = fees.recipients.iter().map(|r| r.address.clone()).collect();
+    let mut weights: Vec<u128> = fees.recipients.iter().map(|r| r.weight as u128).collect();
+    if recipients.is_empty() {
+      recipients.push(game.admin.clone());
+      weights.push(1);
+    }
+    for coin in dust.iter() {
+      let amounts = split_by_weights(coin.amount, &weights);
+      for (recipient, amount) in recipients.iter().zip(amounts) {
+        if !amount.is_zero() {
+          resp = resp.add_submessage(build_dust_transfer_msg(&game.token, recipient, &coin.denom, amount)?);
+        }
+      }
+    }
+
+    // funds held by the contract according to the ledger are part of the dust
+    let ledger = update_ledger(deps.storage, |ledger| ledger.taxed += ledger.held())?;
+
+    // record a summary of the game, including the wallets paid for each
+    // winning cell when its quarter was resolved
+    let quarters: Vec<QuarterSummary> = game
+      .quarters
+      .iter()
+      .map(|quarter| QuarterSummary {
+        name: quarter.name.clone(),
+        winner: quarter.winner,
+        wallets: quarter.winners.clone().unwrap_or_default(),
+      })
+      .collect();
+
+    game.is_closed = true;
+    game.summary = Some(GameSummary {
+      quarters,
+      ledger,
+      dust,
+      finalized_at: env.block.time,
+      is_cleaned_up: false,
+    });
+  } else if game.summary.as_ref().map(|s| s.is_cleaned_up).unwrap_or(false) {
+    return Err(ContractError::AlreadyFinalized {});
+  }
+
+  // remove the next batch of per-player and per-cell state
+  let mut n_removed = 0;
+  n_removed += remove_entries(deps.storage, &PLAYERS, FINALIZE_BATCH_SIZE - n_removed)?;
+  n_removed += remove_entries(deps.storage, &GRID, FINALIZE_BATCH_SIZE - n_removed)?;
+  n_removed += remove_entries(deps.storage, &CELL_TOKENS, FINALIZE_BATCH_SIZE - n_removed)?;
+  n_removed += remove_entries(deps.storage, &CLAIMABLE, FINALIZE_BATCH_SIZE - n_removed)?;
+  n_removed += remove_entries(deps.storage, &CONTRIBUTIONS, FINALIZE_BATCH_SIZE - n_removed)?;
+  n_removed += remove_entries(deps.storage, &REFERRALS, FINALIZE_BATCH_SIZE - n_removed)?;
+  n_removed += remove_entries(deps.storage, &PLAYER_COLORS, FINALIZE_BATCH_SIZE - n_removed)?;
+  n_removed += remove_entries(deps.storage, &INVITE_CODES, FINALIZE_BATCH_SIZE - n_removed)?;
+  n_removed += remove_entries(deps.storage, &POTS, FINALIZE_BATCH_SIZE - n_removed)?;
+
+  // the cleanup is done once a batch comes up short
+  let is_cleaned_up = n_removed < FINALIZE_BATCH_SIZE;
+  if let Some(summary) = &mut game.summary {
+    summary.is_cleaned_up = is_cleaned_up;
+  }
+  GAME.save(deps.storage, &game)?;
+
+  Ok(resp.add_attributes(vec![
+    attr("removed", n_removed.to_string()),
+    attr("is_cleaned_up", is_cleaned_up.to_string()),
+  ]))
+}
+
+/// A game is settled once it's over and there are no claimable balances or
+/// refunds left to claim, either because everything was claimed or because
+/// unclaimed funds were swept.
+fn is_settled(
+  storage: &dyn Storage,
+  game: &Game,
+) -> StdResult<bool> {
+  if !game.is_over() {
+    return Ok(false);
+  }
+  if game.is_closed {
+    return Ok(true);
+  }
+  let ledger = LEDGER.may_load(storage)?.unwrap_or_default();
+  let has_unclaimed_refunds = game
+    .refund_pool
+    .as_ref()
+    .map(|pool| pool.claimed_weight < pool.total_weight)
+    .unwrap_or(false);
+  Ok(ledger.outstanding.is_zero() && !has_unclaimed_refunds)
+}
+
+/// Get the contract's remaining balance of each of the game's tokens.
+fn query_dust(
+  deps: Deps,
+  env: &Env,
+  game: &Game,
+) -> StdResult<Vec<Coin>> {
+  let contract_addr = env.contract.address.clone();
+  let mut balances: Vec<Coin> = match &game.token {
+    Token::Native { denom } => vec![deps.querier.query_balance(contract_addr, denom)?],
+    Token::Natives { denoms } => {
+      let mut balances = Vec::with_capacity(denoms.len());
+      for d in denoms.iter() {
+        balances.push(deps.querier.query_balance(contract_addr.clone(), &d.denom)?);
+      }
+      balances
+    },
+    Token::Cw20 { address } => {
+      let resp: BalanceResponse = deps.querier.query_wasm_smart(
+        address.clone(),
+        &Cw20QueryMsg::Balance {
+          address: contract_addr.into(),
+        },
+      )?;
+      vec![Coin {
+        denom: address.to_string(),
+        amount: resp.balance,
+      }]
+    },
+  };
+  balances.retain(|coin| !coin.amount.is_zero());
+  Ok(balances)
+}
+
+fn build_dust_transfer_msg(
+  token: &Token,
+  recipient: &Addr,
+  denom: &str,
+  amount: Uint128,
+) -> Result<SubMsg, ContractError> {
+  match token {
+    Token::Cw20 { address } => build_cw20_transfer_msg(recipient, address, amount),
+    _ => Ok(SubMsg::new(BankMsg::Send {
+      to_address: recipient.to_string(),
+      amount: vec![Coin::new(amount.u128(), denom)],
+    })),
+  }
+}
+
+/// Remove up to `limit` entries from a map, returning the number removed.
+fn remove_entries<'a, K, T>(
+  storage: &mut dyn Storage,
+  map: &Map<'a, K, T>,
+  limit: usize,
+) -> StdResult<usize>
+where
+  K: PrimaryKey<'a> + KeyDeserialize<Output = K> + 'static,
+  T: Serialize + DeserializeOwned,
+{
+  if limit == 0 {
+    return Ok(0);
+  }
+  let keys: Vec<K> = map
+    .keys(storage, None, None, Order::Ascending)
+    .take(limit)
+    .collect::<StdResult<_>>()?;
+  for key in keys.iter() {
+    map.remove(storage, key.clone());
+  }
+  Ok(keys.len())
+}
@@ -0,0 +1,51 @@
+use crate::{
+  error::ContractError,
+  state::{save_new_player, GAME, INVITE_CODES},
+  util::sha256_hex,
+};
+use cosmwasm_std::{attr, DepsMut, Env, MessageInfo, Response};
+
+pub fn join_with_code(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  code: &str,
+  name: Option<String>,
+  color: Option<String>,
+) -> Result<Response, ContractError> {
+  let game = GAME.load(deps.storage)?;
+
+  // game must be new, not started and not over
+  if game.is_over() {
+    return Err(ContractError::GameOver {});
+  }
+  if game.has_started {
+    return Err(ContractError::AlreadyStarted {});
+  }
+
+  // look up the invite code by the hash of its preimage
+  let hash = sha256_hex(code.as_bytes());
+  let mut invite_code = INVITE_CODES
+    .may_load(deps.storage, hash.clone())?
+    .ok_or(ContractError::InvalidInviteCode {})?;
+
+  if let Some(expires_at) = invite_code.expires_at {
+    if env.block.time >= expires_at {
+      return Err(ContractError::InviteCodeExpired {});
+    }
+  }
+
+  // decrement the number of remaining uses, if the code is limited
+  if let Some(uses_remaining) = invite_code.uses_remaining {
+    if uses_remaining == 0 {
+      return Err(ContractError::InviteCodeExhausted {});
+    }
+    invite_code.uses_remaining = Some(uses_remaining - 1);
+    INVITE_CODES.save(deps.storage, hash, &invite_code)?;
+  }
+
+  // register the sender as a player. Now they can buy cells
+  save_new_player(deps.storage, &info.sender, name, color, game.unique_colors)?;
+
+  Ok(Response::new().add_attributes(vec![attr("action", "join_with_code")]))
+}
@@ -1,5 +1,17 @@
+pub mod add_invite_code;
+pub mod assign_digits;
 pub mod buy_squares;
 pub mod choose_winner;
 pub mod claim_refund;
+pub mod claim_winnings;
+pub mod contribute;
+pub mod finalize;
+pub mod join_with_code;
+pub mod receive;
 pub mod register_player;
+pub mod register_players;
+pub mod remove_invite_code;
 pub mod start_game;
+pub mod sweep;
+pub mod unregister_player;
+pub mod update_profile;
@@ -0,0 +1,56 @@
+use crate::{
+  error::ContractError,
+  execute::{buy_squares::buy_squares_with_cw20, contribute::contribute_cw20},
+  models::Token,
+  msg::{ReceiveMsg, SquaresOrder},
+  state::GAME,
+};
+use cosmwasm_std::{from_binary, DepsMut, Env, MessageInfo, Response};
+use cw20::Cw20ReceiveMsg;
+
+/// Handle CW20 tokens sent to this contract, decoding the embedded message.
+pub fn receive(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+  let game = GAME.load(deps.storage)?;
+
+  // the sender of this message is the CW20 token contract itself, which must
+  // be the game's token. otherwise, anyone could pay with a worthless token.
+  match &game.token {
+    Token::Cw20 { address } if *address == info.sender => {},
+    _ => return Err(ContractError::InvalidCw20Token {}),
+  }
+
+  // the wallet that sent the tokens
+  let sender = deps.api.addr_validate(&wrapper.sender)?;
+
+  match from_binary(&wrapper.msg)? {
+    ReceiveMsg::BuySquares {
+      coordinates,
+      shares,
+      player_name,
+      player_color,
+      merkle_proof,
+      recipient,
+      referrer,
+    } => buy_squares_with_cw20(
+      deps,
+      env,
+      &sender,
+      wrapper.amount,
+      SquaresOrder {
+        coordinates,
+        shares,
+        player_name,
+        player_color,
+        merkle_proof,
+        recipient,
+        referrer,
+      },
+    ),
+    ReceiveMsg::Contribute {} => contribute_cw20(deps, env, &sender, wrapper.amount),
+  }
+}
@@ -1,7 +1,6 @@
 use crate::{
   error::ContractError,
-  models::Player,
-  state::{require_admin, GAME, PLAYERS},
+  state::{require_admin, save_new_player, GAME},
 };
 use cosmwasm_std::{attr, Addr, DepsMut, Env, MessageInfo, Response};
 
@@ -27,23 +26,7 @@ pub fn register_player(
   }
 
   // "register" (i.e. save) the player. Now they can buy cells
-  PLAYERS.update(
-    deps.storage,
-    player_addr.clone(),
-    |some_player| -> Result<Player, ContractError> {
-      if some_player == None {
-        Ok(Player {
-          wallet: player_addr.clone(),
-          positions: None,
-          has_claimed_refund: Some(false),
-          name,
-          color,
-        })
-      } else {
-        Err(ContractError::DuplicatePlayerAddress {})
-      }
-    },
-  )?;
+  save_new_player(deps.storage, player_addr, name, color, game.unique_colors)?;
 
   Ok(Response::new().add_attributes(vec![attr("action", "add_player")]))
 }
@@ -0,0 +1,44 @@
+use crate::{
+  error::ContractError,
+  msg::PlayerRegistration,
+  state::{require_admin, save_new_player, GAME},
+};
+use cosmwasm_std::{attr, DepsMut, Env, MessageInfo, Response};
+
+pub fn register_players(
+  deps: DepsMut,
+  _env: Env,
+  info: MessageInfo,
+  players: Vec<PlayerRegistration>,
+) -> Result<Response, ContractError> {
+  let game = GAME.load(deps.storage)?;
+
+  // address executing this request must be admin
+  require_admin(&game, &info.sender)?;
+
+  // game must be new, not started and not over
+  if game.is_over() {
+    return Err(ContractError::GameOver {});
+  }
+  if game.has_started {
+    return Err(ContractError::AlreadyStarted {});
+  }
+
+  // register each player, failing the whole batch if any wallet is already
+  // registered or appears more than once
+  let n_players = players.len();
+  for player in players.into_iter() {
+    save_new_player(
+      deps.storage,
+      &player.wallet,
+      player.name,
+      player.color,
+      game.unique_colors,
+    )?;
+  }
+
+  Ok(Response::new().add_attributes(vec![
+    attr("action", "add_players"),
+    attr("count", n_players.to_string()),
+  ]))
+}
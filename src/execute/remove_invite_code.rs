@@ -0,0 +1,27 @@
+use crate::{
+  error::ContractError,
+  state::{require_admin, GAME, INVITE_CODES},
+};
+use cosmwasm_std::{attr, DepsMut, Env, MessageInfo, Response};
+
+pub fn remove_invite_code(
+  deps: DepsMut,
+  _env: Env,
+  info: MessageInfo,
+  hash: &str,
+) -> Result<Response, ContractError> {
+  let game = GAME.load(deps.storage)?;
+
+  // address executing this request must be admin
+  require_admin(&game, &info.sender)?;
+
+  let hash = hash.to_lowercase();
+
+  if !INVITE_CODES.has(deps.storage, hash.clone()) {
+    return Err(ContractError::InvalidInviteCode {});
+  }
+
+  INVITE_CODES.remove(deps.storage, hash.clone());
+
+  Ok(Response::new().add_attributes(vec![attr("action", "remove_invite_code"), attr("hash", hash)]))
+}
@@ -0,0 +1,79 @@
+use crate::{
+  error::ContractError,
+  models::SweepRecord,
+  state::{require_admin, update_ledger, GAME, LEDGER},
+  treasury::Treasury,
+};
+use cosmwasm_std::{attr, DepsMut, Env, MessageInfo, Response, Uint128};
+
+/// Send all winnings and refunds that haven't been claimed by the claim
+/// deadline to the treasury, closing the game to further claims.
+pub fn sweep(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+) -> Result<Response, ContractError> {
+  let mut game = GAME.load(deps.storage)?;
+
+  require_admin(&game, &info.sender)?;
+
+  if game.is_closed {
+    return Err(ContractError::GameClosed {});
+  }
+  if !game.is_over() {
+    return Err(ContractError::NotAuthorized {});
+  }
+  match game.claim_deadline {
+    Some(claim_deadline) if env.block.time >= claim_deadline => {},
+    _ => return Err(ContractError::ClaimDeadlineNotReached {}),
+  }
+
+  // unclaimed winnings, including referral rewards, and unclaimed refunds
+  let ledger = LEDGER.may_load(deps.storage)?.unwrap_or_default();
+  let winnings = ledger.outstanding;
+  let refunds = match &mut game.refund_pool {
+    Some(pool) => {
+      let amount = pool.amount - pool.claimed_amount;
+      pool.claimed_amount = pool.amount;
+      pool.claimed_weight = pool.total_weight;
+      amount
+    },
+    None => Uint128::zero(),
+  };
+  let total = winnings + refunds;
+
+  // games with a claim deadline always have a treasury
+  let recipient = game.treasury.clone().ok_or(ContractError::InvalidClaimPeriod {})?;
+
+  let mut treasury = Treasury::load(deps.storage, &game)?;
+  let transfer_msg = treasury.build_transfer_msg(&recipient, total)?;
+  treasury.save(deps.storage)?;
+
+  // claimable balances are left in place for the record, but can no longer
+  // be claimed once the game is closed
+  game.is_closed = true;
+  game.referral_balance = Uint128::zero();
+  game.sweep = Some(SweepRecord {
+    recipient: recipient.clone(),
+    winnings,
+    refunds,
+    swept_at: env.block.time,
+  });
+  GAME.save(deps.storage, &game)?;
+
+  update_ledger(deps.storage, |ledger| {
+    ledger.outstanding -= winnings;
+    ledger.swept += total;
+  })?;
+
+  Ok(
+    Response::new()
+      .add_attributes(vec![
+        attr("action", "sweep"),
+        attr("recipient", recipient.to_string()),
+        attr("winnings", winnings.to_string()),
+        attr("refunds", refunds.to_string()),
+      ])
+      .add_submessages(transfer_msg),
+  )
+}
@@ -0,0 +1,93 @@
+use crate::{
+  error::ContractError,
+  state::{require_admin, update_ledger, GAME, GRID, PLAYERS, PLAYER_COLORS, REFERRALS},
+  treasury::Treasury,
+};
+use cosmwasm_std::{attr, Addr, DepsMut, Env, MessageInfo, Order, Response, StdResult};
+
+pub fn unregister_player(
+  deps: DepsMut,
+  _env: Env,
+  info: MessageInfo,
+  player_addr: &Addr,
+) -> Result<Response, ContractError> {
+  let mut game = GAME.load(deps.storage)?;
+
+  // address executing this request must be admin
+  require_admin(&game, &info.sender)?;
+
+  // game must be new, not started and not over
+  if game.is_over() {
+    return Err(ContractError::GameOver {});
+  }
+  if game.has_started {
+    return Err(ContractError::AlreadyStarted {});
+  }
+
+  let player = PLAYERS
+    .may_load(deps.storage, player_addr.clone())?
+    .ok_or(ContractError::UnknownPlayerAddress {})?;
+
+  // the game can't burn NFTs minted for the player's positions, which can be
+  // transferred freely, so players with NFTs can't be removed
+  if player.positions.iter().flatten().any(|p| p.token_id.is_some()) {
+    return Err(ContractError::PlayerHasNfts {});
+  }
+
+  // refund the total amount spent by the player on the squares they bought
+  let refund_amount = player.paid.unwrap_or_default();
+
+  // remove the player from every cell they're in. we scan the whole grid
+  // rather than just the player's positions, since cells can also be assigned
+  // to players at instantiation.
+  let grid: Vec<_> = GRID
+    .range(deps.storage, None, None, Order::Ascending)
+    .collect::<Result<_, _>>()?;
+  for (coords, mut cell) in grid.into_iter() {
+    if cell.remove_wallet(player_addr) {
+      GRID.save(deps.storage, coords, &cell)?;
+    }
+  }
+
+  PLAYERS.remove(deps.storage, player_addr.clone());
+
+  // free up the player's color for others to use
+  if game.unique_colors {
+    if let Some(color) = &player.color {
+      PLAYER_COLORS.remove(deps.storage, color.clone());
+    }
+  }
+
+  // reverse everything recorded for the player's referrer. no house fee has
+  // been charged on the player's spend yet, since the game hasn't started.
+  if let Some(referrer) = &player.referrer {
+    REFERRALS.update(deps.storage, referrer.clone(), |some_totals| -> StdResult<_> {
+      let mut totals = some_totals.unwrap_or_default();
+      totals.referred_players -= 1;
+      totals.referred_spend -= refund_amount;
+      Ok(totals)
+    })?;
+    game.referred_amount -= refund_amount;
+  }
+
+  // send the player's spend back to them
+  let mut treasury = Treasury::load(deps.storage, &game)?;
+  let transfer_msg = treasury.build_transfer_msg(player_addr, refund_amount)?;
+  treasury.save(deps.storage)?;
+
+  // shrink the prize pool by the amount refunded
+  game.token_amount -= refund_amount;
+  GAME.save(deps.storage, &game)?;
+
+  update_ledger(deps.storage, |ledger| ledger.refunded += refund_amount)?;
+
+  Ok(
+    Response::new()
+      .add_attributes(vec![
+        attr("action", "remove_player"),
+        attr("wallet", player_addr.to_string()),
+        attr("refund_amount", refund_amount.to_string()),
+      ])
+      .add_submessages(transfer_msg),
+  )
+}
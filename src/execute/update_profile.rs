@@ -0,0 +1,43 @@
+use crate::{
+  error::ContractError,
+  state::{reserve_player_color, GAME, PLAYERS, PLAYER_COLORS},
+  util::{normalize_player_color, validate_player_name},
+};
+use cosmwasm_std::{attr, DepsMut, Env, MessageInfo, Response};
+
+pub fn update_profile(
+  deps: DepsMut,
+  _env: Env,
+  info: MessageInfo,
+  name: Option<String>,
+  color: Option<String>,
+) -> Result<Response, ContractError> {
+  let game = GAME.load(deps.storage)?;
+
+  // only existing players can update their profile
+  let mut player = PLAYERS
+    .may_load(deps.storage, info.sender.clone())?
+    .ok_or(ContractError::NotAuthorized {})?;
+
+  // fields that aren't given are left unchanged
+  if let Some(name) = name {
+    validate_player_name(&name)?;
+    player.name = Some(name);
+  }
+
+  if let Some(color) = color {
+    let color = normalize_player_color(&color)?;
+    if game.unique_colors && player.color.as_ref() != Some(&color) {
+      // swap the player's old color for the new one
+      reserve_player_color(deps.storage, &color, &info.sender)?;
+      if let Some(old_color) = &player.color {
+        PLAYER_COLORS.remove(deps.storage, old_color.clone());
+      }
+    }
+    player.color = Some(color);
+  }
+
+  PLAYERS.save(deps.storage, info.sender.clone(), &player)?;
+
+  Ok(Response::new().add_attributes(vec![attr("action", "update_profile")]))
+}
@@ -3,6 +3,9 @@ mod error;
 pub mod execute;
 pub mod models;
 pub mod msg;
+pub mod nft;
+pub mod payout;
 pub mod query;
 pub mod state;
+pub mod treasury;
 mod util;
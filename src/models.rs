@@ -1,29 +1,110 @@
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Coin, Decimal, Timestamp, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::error::ContractError;
+
 pub type GridCoordinates = (u8, u8);
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Token {
   Native { denom: String },
+  Natives { denoms: Vec<AcceptedDenom> },
   Cw20 { address: Addr },
 }
 
+/// A native denom accepted by a game, at a fixed rate. Prices and amounts in
+/// the game's state are given in base units, and the price of a cell in this
+/// denom is its price in base units times the rate.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AcceptedDenom {
+  pub denom: String,
+  pub rate: Decimal,
+}
+
+/// Digit labels of the grid, assigned once sales close. The digit of a row or
+/// column is the last digit of the corresponding team's score.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Digits {
+  /// digit of each row, i.e. of each cell's first coordinate
+  pub rows: Vec<u8>,
+  /// digit of each column, i.e. of each cell's second coordinate
+  pub cols: Vec<u8>,
+}
+
+impl Digits {
+  pub fn get(
+    &self,
+    coords: GridCoordinates,
+  ) -> Option<(u8, u8)> {
+    Some((*self.rows.get(coords.0 as usize)?, *self.cols.get(coords.1 as usize)?))
+  }
+}
+
+/// How sponsor contributions are handled if the game ends up refundable.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SponsorRefundPolicy {
+  /// sponsors claim refunds of their contributions, like players do
+  ReturnToSponsors,
+  /// contributions are added to players' refunds, in proportion to their spend
+  SplitAmongPlayers,
+}
+
+/// What happens to a quarter's share of the guaranteed seed pool if no one
+/// bought the quarter's winning square.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsoldSeedPolicy {
+  /// the seed is sent back to the game creator
+  RefundToCreator,
+  /// the seed rolls over into the remaining quarters, like ticket revenue.
+  /// after the final quarter, it's sent back to the game creator.
+  Rollover,
+  /// the seed is paid to the house fee recipients
+  PayHouse,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Game {
   pub admin: Addr,
   pub name: String,
   pub is_public: bool,
+  pub merkle_root: Option<String>,
   pub can_claim_refund: bool,
   pub has_started: bool,
   pub quarters: Vec<Quarter>,
   pub quarter_index: u8,
   pub max_players_per_cell: Option<u16>,
+  pub unique_colors: bool,
+  pub push_payouts: bool,
   pub teams: Vec<Team>,
+  pub digits: Option<Digits>,
   pub token: Token,
   pub token_amount: Uint128,
+  pub distributed_amount: Uint128,
+  pub fees: FeeConfig,
+  pub fee_config_contract: Option<Addr>,
+  pub creator_fee: Option<CreatorFee>,
+  pub referral_bps: u16,
+  /// spend of referred players whose referrers haven't claimed rewards yet
+  pub referred_amount: Uint128,
+  /// referral rewards withheld from house fees, held until referrers claim
+  pub referral_balance: Uint128,
+  pub sponsor_amount: Uint128,
+  pub sponsor_refund_policy: SponsorRefundPolicy,
+  pub seed_amount: Uint128,
+  pub seed_balance: Uint128,
+  pub unsold_seed_policy: UnsoldSeedPolicy,
+  pub refund_pool: Option<RefundPool>,
+  pub nft_contract: Option<Addr>,
+  pub claim_period: Option<u64>,
+  pub claim_deadline: Option<Timestamp>,
+  pub treasury: Option<Addr>,
+  pub is_closed: bool,
+  pub sweep: Option<SweepRecord>,
+  pub summary: Option<GameSummary>,
 }
 
 impl Game {
@@ -32,11 +113,43 @@ impl Game {
   }
 }
 
+/// House fee taken from each quarter's share of the pot, split between the
+/// recipients according to their weights.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeConfig {
+  pub rate_bps: u16,
+  pub recipients: Vec<FeeRecipient>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeRecipient {
+  pub address: Addr,
+  pub weight: u16,
+}
+
+/// Fee taken from each quarter's share of the pot for the game's creator,
+/// alongside the house fee.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CreatorFee {
+  pub rate_bps: u16,
+  pub recipient: Addr,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Quarter {
   pub winner: Option<GridCoordinates>,
+  /// wallets paid for the winning cell when the quarter was resolved, which
+  /// are the NFT owners at the time if squares are NFTs
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub winners: Option<Vec<Addr>>,
   pub name: Option<String>,
-  pub pct: u8,
+  /// share of the pot, in basis points out of 10,000
+  #[serde(default)]
+  pub bps: u16,
+  /// deprecated share of the pot, in whole percent, which is converted to
+  /// basis points when the game is created
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub pct: Option<u8>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -49,12 +162,80 @@ pub struct Team {
 pub struct Cell {
   pub wallets: Option<Vec<Addr>>,
   pub price: Uint128,
+  /// number of shares held by each wallet, in the same order as wallets.
+  /// wallets without a share count hold a single share.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub shares: Option<Vec<u16>>,
+}
+
+impl Cell {
+  /// Get the number of shares held by each wallet in the cell.
+  pub fn share_counts(&self) -> Vec<u16> {
+    let n_wallets = self.wallets.as_ref().map(|w| w.len()).unwrap_or(0);
+    let mut counts = self.shares.clone().unwrap_or_default();
+    counts.resize(n_wallets, 1);
+    counts
+  }
+
+  pub fn total_shares(&self) -> u32 {
+    self.share_counts().iter().map(|n| *n as u32).sum()
+  }
+
+  /// Add shares for the given wallet, which is added to the cell if it isn't
+  /// already in it. Fails if the wallet would hold more shares than fit in a
+  /// share count.
+  pub fn add_shares(
+    &mut self,
+    wallet: &Addr,
+    n_shares: u16,
+  ) -> Result<(), ContractError> {
+    let mut counts = self.share_counts();
+    let mut wallets = self.wallets.clone().unwrap_or_default();
+    if let Some(i) = wallets.iter().position(|addr| addr == wallet) {
+      counts[i] = counts[i]
+        .checked_add(n_shares)
+        .ok_or(ContractError::InvalidShareCount {})?;
+    } else {
+      wallets.push(wallet.clone());
+      counts.push(n_shares);
+    }
+    self.wallets = Some(wallets);
+    self.shares = Some(counts);
+    Ok(())
+  }
+
+  /// Remove the given wallet and its shares from the cell, returning whether
+  /// the wallet was in it.
+  pub fn remove_wallet(
+    &mut self,
+    wallet: &Addr,
+  ) -> bool {
+    let mut counts = self.share_counts();
+    let mut wallets = self.wallets.take().unwrap_or_default();
+    let some_index = wallets.iter().position(|addr| addr == wallet);
+    if let Some(i) = some_index {
+      wallets.remove(i);
+      counts.remove(i);
+    }
+    if wallets.is_empty() {
+      self.wallets = None;
+      self.shares = None;
+    } else {
+      self.wallets = Some(wallets);
+      self.shares = Some(counts);
+    }
+    some_index.is_some()
+  }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Position {
   pub coords: GridCoordinates,
   pub quarter_index: u8,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub shares: Option<u16>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub token_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -64,4 +245,154 @@ pub struct Player {
   pub color: Option<String>,
   pub positions: Option<Vec<Position>>,
   pub has_claimed_refund: Option<bool>,
+  pub referrer: Option<Addr>,
+  pub paid: Option<Uint128>,
+}
+
+/// Running totals for a wallet that has referred players to the game.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct ReferralTotals {
+  pub referred_players: u32,
+  pub referred_spend: Uint128,
+  /// amount actually paid to the referrer, once claimed
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub claimed_rewards: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InviteCode {
+  pub hash: String,
+  pub uses_remaining: Option<u32>,
+  pub expires_at: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Contribution {
+  pub sponsor: Addr,
+  pub amount: Uint128,
+  pub has_claimed_refund: bool,
+}
+
+/// Running totals of the funds moved into and out of the contract.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Ledger {
+  /// ticket sales, sponsor contributions and the seed pool
+  pub collected: Uint128,
+  /// prizes and referral rewards sent to wallets
+  pub paid: Uint128,
+  /// house and creator fees
+  pub taxed: Uint128,
+  /// refunds sent to players, sponsors and the game creator
+  pub refunded: Uint128,
+  /// prizes and referral rewards credited to wallets but not yet claimed
+  pub outstanding: Uint128,
+  /// unclaimed winnings and refunds sent to the treasury after the deadline
+  pub swept: Uint128,
+}
+
+impl Ledger {
+  /// Funds held by the contract, including claimable balances.
+  pub fn held(&self) -> Uint128 {
+    self
+      .collected
+      .saturating_sub(self.paid)
+      .saturating_sub(self.taxed)
+      .saturating_sub(self.refunded)
+      .saturating_sub(self.swept)
+  }
+
+  /// Funds held by the contract that aren't owed to anyone yet, or None if
+  /// the outflows exceed the inflows.
+  pub fn unallocated(&self) -> Option<Uint128> {
+    let outflows = self
+      .paid
+      .checked_add(self.taxed)
+      .and_then(|amount| amount.checked_add(self.refunded))
+      .and_then(|amount| amount.checked_add(self.outstanding))
+      .and_then(|amount| amount.checked_add(self.swept))
+      .ok()?;
+    self.collected.checked_sub(outflows).ok()
+  }
+}
+
+/// The undistributed balance of the pot left when a game becomes refundable,
+/// which is returned to players, and possibly sponsors, in proportion to the
+/// amount each of them paid into the pot.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RefundPool {
+  pub amount: Uint128,
+  /// total amount paid into the pot by everyone entitled to a refund
+  pub total_weight: Uint128,
+  pub claimed_weight: Uint128,
+  pub claimed_amount: Uint128,
+  /// whether sponsors are entitled to refunds of their contributions
+  pub includes_sponsors: bool,
+}
+
+/// Settings for the companion cw721 contract instantiated by the game, which
+/// mints an NFT for each position bought.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftConfig {
+  pub code_id: u64,
+  pub name: String,
+  pub symbol: String,
+}
+
+/// An NFT minted for a position in a cell, representing the position's shares,
+/// which are owed to whoever owns the NFT.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CellToken {
+  pub token_id: String,
+  /// the wallet the position was bought for
+  pub wallet: Addr,
+  pub shares: u16,
+}
+
+/// Unclaimed funds swept to the treasury after the claim deadline.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SweepRecord {
+  pub recipient: Addr,
+  pub winnings: Uint128,
+  pub refunds: Uint128,
+  pub swept_at: Timestamp,
+}
+
+/// Compact record of a finalized game, kept after per-player and per-cell
+/// state has been removed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GameSummary {
+  pub quarters: Vec<QuarterSummary>,
+  pub ledger: Ledger,
+  /// funds left in the contract's balance, sent to the house fee recipients
+  pub dust: Vec<Coin>,
+  pub finalized_at: Timestamp,
+  /// whether all per-player and per-cell state has been removed
+  pub is_cleaned_up: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QuarterSummary {
+  pub name: Option<String>,
+  pub winner: Option<GridCoordinates>,
+  pub wallets: Vec<Addr>,
+}
+
+#[cfg(test)]
+mod tests {
+  use cosmwasm_std::{Addr, Uint128};
+
+  use super::Cell;
+
+  #[test]
+  fn add_shares_rejects_share_count_overflow() {
+    let wallet = Addr::unchecked("player");
+    let mut cell = Cell {
+      wallets: None,
+      price: Uint128::one(),
+      shares: None,
+    };
+    cell.add_shares(&wallet, 40_000).unwrap();
+    assert!(cell.add_shares(&wallet, 40_000).is_err());
+    assert_eq!(cell.share_counts(), vec![40_000]);
+  }
 }
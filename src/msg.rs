@@ -1,8 +1,12 @@
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Coin, Empty, Timestamp, Uint128};
+use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::models::{Cell, Game, GridCoordinates, Player, Quarter, Team, Token};
+use crate::models::{
+  Cell, Contribution, FeeConfig, FeeRecipient, Game, GridCoordinates, Ledger, NftConfig, Player, Quarter,
+  ReferralTotals, SponsorRefundPolicy, Team, Token, UnsoldSeedPolicy,
+};
 
 /// Initial contract state.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -15,6 +19,20 @@ pub struct InstantiateMsg {
   pub quarters: Vec<Quarter>,
   pub grid: Vec<Cell>,
   pub token: Token,
+  pub merkle_root: Option<String>,
+  pub unique_colors: Option<bool>,
+  pub push_payouts: Option<bool>,
+  pub fees: FeeConfig,
+  pub fee_config_contract: Option<Addr>,
+  pub creator_fee_bps: Option<u16>,
+  pub creator_fee_recipient: Option<Addr>,
+  pub referral_bps: Option<u16>,
+  pub sponsor_refund_policy: Option<SponsorRefundPolicy>,
+  pub seed_amount: Option<Uint128>,
+  pub unsold_seed_policy: Option<UnsoldSeedPolicy>,
+  pub nft: Option<NftConfig>,
+  pub claim_period: Option<u64>,
+  pub treasury: Option<Addr>,
 }
 
 /// Executable contract endpoints.
@@ -26,18 +44,101 @@ pub enum ExecuteMsg {
     name: Option<String>,
     color: Option<String>,
   },
+  RegisterPlayers {
+    players: Vec<PlayerRegistration>,
+  },
+  UnregisterPlayer {
+    wallet: Addr,
+  },
+  AddInviteCode {
+    hash: String,
+    max_uses: Option<u32>,
+    expires_at: Option<Timestamp>,
+  },
+  RemoveInviteCode {
+    hash: String,
+  },
+  JoinWithCode {
+    code: String,
+    name: Option<String>,
+    color: Option<String>,
+  },
+  UpdateProfile {
+    name: Option<String>,
+    color: Option<String>,
+  },
   StartGame {},
+  AssignDigits {
+    rows: Vec<u8>,
+    cols: Vec<u8>,
+  },
   ClaimRefund {},
+  ClaimWinnings {},
+  Sweep {},
+  Finalize {},
   BuySquares {
     coordinates: Vec<GridCoordinates>,
+    shares: Option<Vec<u16>>,
     player_name: Option<String>,
     player_color: Option<String>,
+    merkle_proof: Option<Vec<String>>,
+    recipient: Option<Addr>,
+    referrer: Option<Addr>,
   },
+  BuySquaresWithAllowance {
+    coordinates: Vec<GridCoordinates>,
+    shares: Option<Vec<u16>>,
+    player_name: Option<String>,
+    player_color: Option<String>,
+    merkle_proof: Option<Vec<String>>,
+    recipient: Option<Addr>,
+    referrer: Option<Addr>,
+  },
+  Contribute {},
+  Receive(Cw20ReceiveMsg),
   ChooseWinner {
     winner: GridCoordinates,
   },
 }
 
+/// Wallet and optional display settings for a player registered by the admin.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PlayerRegistration {
+  pub wallet: Addr,
+  pub name: Option<String>,
+  pub color: Option<String>,
+}
+
+/// Messages embedded in CW20 tokens sent to this contract.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+  BuySquares {
+    coordinates: Vec<GridCoordinates>,
+    shares: Option<Vec<u16>>,
+    player_name: Option<String>,
+    player_color: Option<String>,
+    merkle_proof: Option<Vec<String>>,
+    recipient: Option<Addr>,
+    referrer: Option<Addr>,
+  },
+  Contribute {},
+}
+
+/// Squares to buy, along with the settings used to lazy init the player.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SquaresOrder {
+  pub coordinates: Vec<GridCoordinates>,
+  /// number of shares to buy in each cell, in the same order as coordinates.
+  /// one share of each cell is bought by default.
+  pub shares: Option<Vec<u16>>,
+  pub player_name: Option<String>,
+  pub player_color: Option<String>,
+  pub merkle_proof: Option<Vec<String>>,
+  pub recipient: Option<Addr>,
+  pub referrer: Option<Addr>,
+}
+
 /// Custom contract query endpoints.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -46,6 +147,31 @@ pub enum QueryMsg {
     with_players: Option<bool>,
     with_grid: Option<bool>,
   },
+  Claimable {
+    wallet: Addr,
+  },
+  Referrals {
+    referrer: Addr,
+  },
+  Contributions {
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+  },
+  Ledger {},
+  Players {
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+  },
+  Grid {
+    start_after: Option<GridCoordinates>,
+    limit: Option<u32>,
+  },
+  Player {
+    wallet: Addr,
+  },
+  Cell {
+    coords: GridCoordinates,
+  },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -54,3 +180,122 @@ pub struct GameResponse {
   pub grid: Option<Vec<Cell>>,
   pub players: Option<Vec<Player>>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimableResponse {
+  pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContributionsResponse {
+  pub contributions: Vec<Contribution>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PlayersResponse {
+  pub players: Vec<Player>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GridCell {
+  pub coords: GridCoordinates,
+  pub cell: Cell,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GridResponse {
+  pub cells: Vec<GridCell>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PlayerResponse {
+  pub player: Option<Player>,
+  pub paid: Uint128,
+  /// winnings and referral rewards that can be claimed
+  pub claimable: Uint128,
+  pub has_claimed_refund: bool,
+  /// refund that can currently be claimed, if the game is refundable
+  pub refund_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CellOwner {
+  pub wallet: Addr,
+  pub shares: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CellResponse {
+  pub coords: GridCoordinates,
+  pub cell: Cell,
+  /// wallets owed the cell's prizes, which are the current NFT owners if
+  /// squares are NFTs
+  pub owners: Vec<CellOwner>,
+  /// number of shares left to buy, if the number of players per cell is capped
+  pub remaining_capacity: Option<u32>,
+  /// the cell's row and column digits, once assigned
+  pub digits: Option<(u8, u8)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LedgerResponse {
+  pub ledger: Ledger,
+  pub unallocated: Uint128,
+  pub pots: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralsResponse {
+  pub referrer: Addr,
+  pub totals: ReferralTotals,
+  /// referral rewards earned so far, or paid, once claimed
+  pub rewards: Uint128,
+}
+
+/// Query sent to a game's external fee config contract, if any, to get the
+/// house fee configuration in effect when paying out each quarter.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeConfigQueryMsg {
+  FeeConfig {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeConfigResponse {
+  pub rate_bps: u16,
+  pub recipients: Vec<FeeRecipient>,
+}
+
+/// Instantiate msg sent to the game's companion cw721 contract, if any. The
+/// game is the contract's minter.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw721InstantiateMsg {
+  pub name: String,
+  pub symbol: String,
+  pub minter: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw721ExecuteMsg {
+  Mint {
+    token_id: String,
+    owner: String,
+    token_uri: Option<String>,
+    extension: Empty,
+  },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw721QueryMsg {
+  OwnerOf {
+    token_id: String,
+    include_expired: Option<bool>,
+  },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnerOfResponse {
+  pub owner: String,
+}
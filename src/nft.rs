@@ -0,0 +1,193 @@
+use cosmwasm_std::{
+  to_binary, Addr, Deps, DepsMut, Env, Reply, Response, StdError, StdResult, Storage, SubMsg, Uint128, WasmMsg,
+};
+use cw_utils::parse_reply_instantiate_data;
+
+use crate::{
+  error::ContractError,
+  models::{Cell, CellToken, Game, GridCoordinates, NftConfig},
+  msg::{Cw721ExecuteMsg, Cw721InstantiateMsg, Cw721QueryMsg, OwnerOfResponse},
+  state::{CELL_TOKENS, GAME, NFT_TOKEN_COUNT},
+};
+
+pub const INSTANTIATE_NFT_REPLY_ID: u64 = 1;
+
+// max number of NFTs minted for positions in a single cell, which bounds the
+// number of owner_of queries made when the cell wins a quarter
+pub const MAX_CELL_TOKENS: usize = 25;
+
+/// Build the submsg that instantiates the game's companion cw721 contract,
+/// with the game as its minter. The contract's address is saved on reply.
+pub fn build_instantiate_nft_msg(
+  env: &Env,
+  admin: &Addr,
+  config: &NftConfig,
+) -> StdResult<SubMsg> {
+  Ok(SubMsg::reply_on_success(
+    WasmMsg::Instantiate {
+      admin: Some(admin.to_string()),
+      code_id: config.code_id,
+      msg: to_binary(&Cw721InstantiateMsg {
+        name: config.name.clone(),
+        symbol: config.symbol.clone(),
+        minter: env.contract.address.to_string(),
+      })?,
+      funds: vec![],
+      label: config.name.clone(),
+    },
+    INSTANTIATE_NFT_REPLY_ID,
+  ))
+}
+
+/// Save the address of the companion cw721 contract once it's instantiated.
+pub fn handle_instantiate_nft_reply(
+  deps: DepsMut,
+  msg: Reply,
+) -> Result<Response, ContractError> {
+  let data = parse_reply_instantiate_data(msg)?;
+  let nft_contract = deps.api.addr_validate(&data.contract_address)?;
+  GAME.update(deps.storage, |mut game| -> StdResult<_> {
+    game.nft_contract = Some(nft_contract.clone());
+    Ok(game)
+  })?;
+  Ok(Response::new().add_attribute("nft_contract", nft_contract.to_string()))
+}
+
+/// Mint an NFT for a position in a cell to the wallet it was bought for,
+/// returning the new token's ID and the mint msg. Fails if the cell already
+/// has the max number of NFTs.
+pub fn mint_cell_token(
+  storage: &mut dyn Storage,
+  nft_contract: &Addr,
+  coords: GridCoordinates,
+  wallet: &Addr,
+  shares: u16,
+) -> Result<(String, WasmMsg), ContractError> {
+  let token_number = NFT_TOKEN_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+  NFT_TOKEN_COUNT.save(storage, &token_number)?;
+
+  let token_id = token_number.to_string();
+  CELL_TOKENS.update(storage, coords, |some_tokens| -> Result<_, ContractError> {
+    let mut tokens = some_tokens.unwrap_or_default();
+    if tokens.len() >= MAX_CELL_TOKENS {
+      return Err(ContractError::CellSoldOut {});
+    }
+    tokens.push(CellToken {
+      token_id: token_id.clone(),
+      wallet: wallet.clone(),
+      shares,
+    });
+    Ok(tokens)
+  })?;
+
+  let msg = WasmMsg::Execute {
+    contract_addr: nft_contract.to_string(),
+    msg: to_binary(&Cw721ExecuteMsg::Mint {
+      token_id: token_id.clone(),
+      owner: wallet.to_string(),
+      token_uri: None,
+      extension: Default::default(),
+    })?,
+    funds: vec![],
+  };
+
+  Ok((token_id, msg))
+}
+
+/// Get the wallets owed a cell's prizes, along with the number of shares owed
+/// to each. Shares represented by an NFT are owed to the NFT's current owner
+/// instead of the wallet the position was bought for. If the NFT doesn't exist
+/// anymore, because it was burned, its shares stay with that wallet. Any other
+/// error querying the cw721 contract fails, rather than paying the wrong
+/// wallets.
+pub fn load_cell_owners(
+  deps: Deps,
+  game: &Game,
+  coords: GridCoordinates,
+  cell: &Cell,
+) -> StdResult<Vec<(Addr, Uint128)>> {
+  let mut owners: Vec<(Addr, Uint128)> = cell
+    .wallets
+    .clone()
+    .unwrap_or_default()
+    .into_iter()
+    .zip(cell.share_counts().into_iter().map(Uint128::from))
+    .collect();
+
+  if let Some(nft_contract) = &game.nft_contract {
+    for token in CELL_TOKENS.may_load(deps.storage, coords)?.unwrap_or_default() {
+      let result: StdResult<OwnerOfResponse> = deps.querier.query_wasm_smart(
+        nft_contract.clone(),
+        &Cw721QueryMsg::OwnerOf {
+          token_id: token.token_id.clone(),
+          include_expired: None,
+        },
+      );
+      let owner = match result {
+        Ok(resp) => deps.api.addr_validate(&resp.owner)?,
+        Err(err) if is_not_found_error(&err) => continue,
+        Err(err) => return Err(err),
+      };
+      if owner == token.wallet {
+        continue;
+      }
+      let shares = Uint128::from(token.shares);
+      if let Some(entry) = owners.iter_mut().find(|(addr, _)| *addr == token.wallet) {
+        entry.1 = entry.1.saturating_sub(shares);
+      }
+      match owners.iter_mut().find(|(addr, _)| *addr == owner) {
+        Some(entry) => entry.1 += shares,
+        None => owners.push((owner, shares)),
+      }
+    }
+    owners.retain(|(_, shares)| !shares.is_zero());
+  }
+
+  Ok(owners)
+}
+
+/// Check whether an error returned by a cw721 query means the token doesn't
+/// exist. Errors from other contracts reach the querier as generic errors, so
+/// this relies on the message cw721-base returns for missing tokens.
+fn is_not_found_error(err: &StdError) -> bool {
+  match err {
+    StdError::NotFound { .. } => true,
+    StdError::GenericErr { msg, .. } => msg.contains("not found"),
+    _ => false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use cosmwasm_std::{testing::mock_dependencies, Addr, StdError};
+
+  use super::*;
+
+  #[test]
+  fn only_missing_tokens_are_not_found() {
+    assert!(is_not_found_error(&StdError::generic_err(
+      "Querier contract error: cw721_base::state::TokenInfo<core::option::Option<cosmwasm_std::results::empty::Empty>> not found"
+    )));
+    assert!(is_not_found_error(&StdError::not_found("token")));
+    assert!(!is_not_found_error(&StdError::generic_err(
+      "Querier system error: No such contract: nft"
+    )));
+    assert!(!is_not_found_error(&StdError::parse_err(
+      "OwnerOfResponse",
+      "missing field `owner`"
+    )));
+  }
+
+  #[test]
+  fn cells_have_a_max_number_of_tokens() {
+    let mut deps = mock_dependencies();
+    let nft_contract = Addr::unchecked("nft");
+    let wallet = Addr::unchecked("player");
+    for _ in 0..MAX_CELL_TOKENS {
+      mint_cell_token(&mut deps.storage, &nft_contract, (0, 0), &wallet, 1).unwrap();
+    }
+    let result = mint_cell_token(&mut deps.storage, &nft_contract, (0, 0), &wallet, 1);
+    assert!(matches!(result, Err(ContractError::CellSoldOut {})));
+    assert!(mint_cell_token(&mut deps.storage, &nft_contract, (0, 1), &wallet, 1).is_ok());
+  }
+}
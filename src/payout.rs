@@ -0,0 +1,380 @@
+use cosmwasm_std::Uint128;
+
+use crate::{
+  models::{FeeConfig, Game, RefundPool, SponsorRefundPolicy},
+  util::compute_amount_from_bps,
+};
+
+/// The amounts paid out for a quarter with at least one winner. The prizes,
+/// fees, creator fee and the amount withheld from the fees for referral
+/// rewards always add up to the quarter's share of the pot.
+pub struct QuarterPayout {
+  pub prizes: Vec<Uint128>,
+  pub fees: Vec<Uint128>,
+  pub creator_fee: Uint128,
+  pub referral_amount: Uint128,
+}
+
+/// Split an amount into parts proportional to the given weights. Units left
+/// over from rounding down are handed out one at a time to the parts with
+/// nonzero weight, in order, so the parts always add up to the amount. If all
+/// weights are zero, the last part gets the whole amount.
+pub fn split_by_weights(
+  amount: Uint128,
+  weights: &[u128],
+) -> Vec<Uint128> {
+  let total_weight: u128 = weights.iter().sum();
+  let mut parts: Vec<Uint128> = vec![Uint128::zero(); weights.len()];
+
+  if parts.is_empty() {
+    return parts;
+  }
+  if total_weight == 0 {
+    parts[weights.len() - 1] = amount;
+    return parts;
+  }
+
+  let mut remainder = amount;
+  for (part, weight) in parts.iter_mut().zip(weights.iter()) {
+    *part = amount.multiply_ratio(*weight, total_weight);
+    remainder -= *part;
+  }
+
+  // the remainder is always less than the number of parts with nonzero weight
+  for (part, weight) in parts.iter_mut().zip(weights.iter()) {
+    if remainder.is_zero() {
+      break;
+    }
+    if *weight > 0 {
+      *part += Uint128::one();
+      remainder -= Uint128::one();
+    }
+  }
+
+  parts
+}
+
+/// Compute the share of the pot owed to the current quarter, including fees.
+/// This is the undistributed balance of the pot, split between the current
+/// and remaining quarters according to their basis points. The final quarter
+/// always gets the entire undistributed balance, so no dust is left behind.
+pub fn compute_quarter_share(game: &Game) -> Uint128 {
+  let undistributed_amount = game.token_amount - game.distributed_amount;
+  let remaining_quarters = &game.quarters[game.quarter_index as usize..];
+  let splits: Vec<u128> = remaining_quarters.iter().map(|q| q.bps as u128).collect();
+  split_by_weights(undistributed_amount, &splits)[0]
+}
+
+/// Compute the part of a quarter's share of the pot that comes from the
+/// guaranteed seed pool. The seed's undistributed balance is spread over the
+/// pot's undistributed balance in proportion to each quarter's share.
+pub fn compute_seed_portion(
+  game: &Game,
+  quarter_share: Uint128,
+) -> Uint128 {
+  let undistributed_amount = game.token_amount - game.distributed_amount;
+  if undistributed_amount.is_zero() {
+    return Uint128::zero();
+  }
+  game.seed_balance.multiply_ratio(quarter_share, undistributed_amount)
+}
+
+/// Split a quarter's share of the pot into a house fee, itself split between
+/// the fee recipients according to their weights, the creator fee, and prizes
+/// split between the winners in proportion to their shares. The creator fee
+/// is reduced if, combined with the house fee, it would exceed the quarter's
+/// share. Referral rewards are withheld from the house fee, as referral_bps of
+/// the part of the fee generated by referred players' spend.
+pub fn compute_quarter_payout(
+  quarter_share: Uint128,
+  winner_shares: &[u128],
+  fees: &FeeConfig,
+  creator_fee_bps: u16,
+  game: &Game,
+) -> QuarterPayout {
+  let fee_amount = compute_amount_from_bps(quarter_share, fees.rate_bps);
+  let creator_fee = compute_amount_from_bps(quarter_share, creator_fee_bps).min(quarter_share - fee_amount);
+  let referral_amount = compute_referral_amount(fee_amount, game);
+  let fee_weights: Vec<u128> = fees.recipients.iter().map(|r| r.weight as u128).collect();
+  QuarterPayout {
+    prizes: split_by_weights(quarter_share - fee_amount - creator_fee, winner_shares),
+    fees: split_by_weights(fee_amount - referral_amount, &fee_weights),
+    creator_fee,
+    referral_amount,
+  }
+}
+
+/// Compute the referral rewards owed out of a house fee actually charged. The
+/// part of the fee generated by referred players is in proportion to their
+/// share of the pot, so the rewards never exceed the fee.
+pub fn compute_referral_amount(
+  fee_amount: Uint128,
+  game: &Game,
+) -> Uint128 {
+  if game.token_amount.is_zero() {
+    return Uint128::zero();
+  }
+  let referred_fee_amount = fee_amount.multiply_ratio(game.referred_amount.min(game.token_amount), game.token_amount);
+  compute_amount_from_bps(referred_fee_amount, game.referral_bps)
+}
+
+/// Compute the amount paid to a referrer claiming rewards for the given spend
+/// of the players it referred, out of the referral rewards withheld from house
+/// fees, in proportion to its share of all referred spend. The last claimant
+/// gets whatever is left, so no dust is left behind.
+pub fn compute_referral_payout(
+  game: &Game,
+  referred_spend: Uint128,
+) -> Uint128 {
+  if referred_spend >= game.referred_amount {
+    game.referral_balance
+  } else {
+    game
+      .referral_balance
+      .multiply_ratio(referred_spend, game.referred_amount)
+  }
+}
+
+/// Create the refund pool for a game that has just become refundable, out of
+/// the pot's undistributed balance.
+/// Sponsor contributions are returned to sponsors unless they're split among
+/// players. If no squares were sold, there's no one to split them with.
+pub fn create_refund_pool(game: &Game) -> RefundPool {
+  let ticket_amount = game.token_amount - game.sponsor_amount - game.seed_amount;
+  let includes_sponsors =
+    game.sponsor_refund_policy == SponsorRefundPolicy::ReturnToSponsors || ticket_amount.is_zero();
+  RefundPool {
+    amount: game.token_amount - game.distributed_amount,
+    total_weight: if includes_sponsors {
+      ticket_amount + game.sponsor_amount
+    } else {
+      ticket_amount
+    },
+    claimed_weight: Uint128::zero(),
+    claimed_amount: Uint128::zero(),
+    includes_sponsors,
+  }
+}
+
+/// Compute the refund owed for the given weight, i.e. the amount paid into the
+/// pot by the claimant. The last claimant gets whatever is left of the pool,
+/// so no dust is left behind.
+pub fn compute_refund(
+  pool: &RefundPool,
+  weight: Uint128,
+) -> Uint128 {
+  if pool.claimed_weight + weight >= pool.total_weight {
+    pool.amount - pool.claimed_amount
+  } else {
+    pool.amount.multiply_ratio(weight, pool.total_weight)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use cosmwasm_std::{Addr, Uint128};
+
+  use super::*;
+  use crate::models::{FeeRecipient, Quarter, Token, UnsoldSeedPolicy};
+
+  fn build_game(
+    token_amount: u128,
+    distributed_amount: u128,
+    bps: &[u16],
+    quarter_index: u8,
+  ) -> Game {
+    Game {
+      admin: Addr::unchecked("creator"),
+      name: "game".to_owned(),
+      is_public: true,
+      merkle_root: None,
+      can_claim_refund: false,
+      has_started: true,
+      quarters: bps
+        .iter()
+        .map(|bps| Quarter {
+          winner: None,
+          winners: None,
+          name: None,
+          bps: *bps,
+          pct: None,
+        })
+        .collect(),
+      quarter_index,
+      max_players_per_cell: None,
+      unique_colors: false,
+      push_payouts: false,
+      teams: vec![],
+      digits: None,
+      token: Token::Native {
+        denom: "ujuno".to_owned(),
+      },
+      token_amount: Uint128::new(token_amount),
+      distributed_amount: Uint128::new(distributed_amount),
+      fees: build_fee_config(0),
+      fee_config_contract: None,
+      creator_fee: None,
+      referral_bps: 0,
+      referred_amount: Uint128::zero(),
+      referral_balance: Uint128::zero(),
+      sponsor_amount: Uint128::zero(),
+      sponsor_refund_policy: SponsorRefundPolicy::ReturnToSponsors,
+      seed_amount: Uint128::zero(),
+      seed_balance: Uint128::zero(),
+      unsold_seed_policy: UnsoldSeedPolicy::Rollover,
+      refund_pool: None,
+      nft_contract: None,
+      claim_period: None,
+      claim_deadline: None,
+      treasury: None,
+      is_closed: false,
+      sweep: None,
+      summary: None,
+    }
+  }
+
+  fn build_fee_config(rate_bps: u16) -> FeeConfig {
+    FeeConfig {
+      rate_bps,
+      recipients: vec![FeeRecipient {
+        address: Addr::unchecked("house"),
+        weight: 1,
+      }],
+    }
+  }
+
+  #[test]
+  fn referral_rewards_come_out_of_the_fee_charged() {
+    let fees = build_fee_config(1000);
+
+    // referred players paid half the pot, so they generated half of the fee
+    let mut game = build_game(400, 0, &[10000], 0);
+    game.referred_amount = Uint128::new(200);
+    game.referral_bps = 5000;
+    let payout = compute_quarter_payout(Uint128::new(100), &[1], &fees, 0, &game);
+    assert_eq!(payout.referral_amount, Uint128::new(2));
+    assert_eq!(payout.fees, vec![Uint128::new(8)]);
+    assert_eq!(payout.prizes, vec![Uint128::new(90)]);
+
+    // winners never pay for referrals, even if every player was referred
+    game.referred_amount = Uint128::new(400);
+    game.referral_bps = 10000;
+    let payout = compute_quarter_payout(Uint128::new(100), &[1], &fees, 0, &game);
+    assert_eq!(payout.referral_amount, Uint128::new(10));
+    assert_eq!(payout.fees, vec![Uint128::zero()]);
+    assert_eq!(payout.prizes, vec![Uint128::new(90)]);
+
+    // no fee, no referral rewards
+    let payout = compute_quarter_payout(Uint128::new(100), &[1], &build_fee_config(0), 0, &game);
+    assert!(payout.referral_amount.is_zero());
+    assert_eq!(payout.prizes, vec![Uint128::new(100)]);
+  }
+
+  #[test]
+  fn split_by_weights_hands_out_remainder_in_order() {
+    let parts = split_by_weights(Uint128::new(10), &[1, 1, 1]);
+    assert_eq!(parts, vec![Uint128::new(4), Uint128::new(3), Uint128::new(3)]);
+
+    // parts with zero weight never get any of the remainder
+    let parts = split_by_weights(Uint128::new(11), &[0, 2, 0, 1]);
+    assert_eq!(
+      parts,
+      vec![Uint128::zero(), Uint128::new(8), Uint128::zero(), Uint128::new(3)]
+    );
+
+    let parts = split_by_weights(Uint128::new(1_000_003), &[3, 7, 11]);
+    assert_eq!(parts.iter().sum::<Uint128>(), Uint128::new(1_000_003));
+  }
+
+  #[test]
+  fn split_by_weights_with_zero_weights_gives_all_to_last_part() {
+    let parts = split_by_weights(Uint128::new(10), &[0, 0, 0]);
+    assert_eq!(parts, vec![Uint128::zero(), Uint128::zero(), Uint128::new(10)]);
+    assert!(split_by_weights(Uint128::new(10), &[]).is_empty());
+  }
+
+  #[test]
+  fn final_quarter_takes_the_full_balance() {
+    // 1001 split 25/25/25/25 leaves a unit of dust on the first quarter
+    let game = build_game(1001, 0, &[2500, 2500, 2500, 2500], 0);
+    assert_eq!(compute_quarter_share(&game), Uint128::new(251));
+
+    // whatever is left for the final quarter is paid out in full
+    let game = build_game(1001, 751, &[2500, 2500, 2500, 2500], 3);
+    assert_eq!(compute_quarter_share(&game), Uint128::new(250));
+
+    // even if the final quarter's bps were zeroed out
+    let game = build_game(1001, 500, &[2500, 2500, 5000, 0], 3);
+    assert_eq!(compute_quarter_share(&game), Uint128::new(501));
+  }
+
+  #[test]
+  fn quarter_payout_adds_up_to_quarter_share() {
+    let fees = FeeConfig {
+      rate_bps: 333,
+      recipients: vec![
+        FeeRecipient {
+          address: Addr::unchecked("house"),
+          weight: 2,
+        },
+        FeeRecipient {
+          address: Addr::unchecked("partner"),
+          weight: 1,
+        },
+      ],
+    };
+    let mut game = build_game(1_000_001, 0, &[10000], 0);
+    game.referral_bps = 7500;
+    for quarter_share in [0u128, 1, 7, 100, 9_999, 1_000_001] {
+      for referred_amount in [0u128, 5, 1_000, 1_000_001] {
+        game.referred_amount = Uint128::new(referred_amount);
+        let payout = compute_quarter_payout(Uint128::new(quarter_share), &[3, 1, 1], &fees, 150, &game);
+        let total = payout.prizes.iter().sum::<Uint128>()
+          + payout.fees.iter().sum::<Uint128>()
+          + payout.creator_fee
+          + payout.referral_amount;
+        assert_eq!(total, Uint128::new(quarter_share));
+      }
+    }
+  }
+
+  #[test]
+  fn creator_fee_never_exceeds_what_house_fee_leaves() {
+    let game = build_game(100, 0, &[10000], 0);
+    let payout = compute_quarter_payout(Uint128::new(100), &[1], &build_fee_config(9000), 2000, &game);
+    assert_eq!(payout.fees, vec![Uint128::new(90)]);
+    assert_eq!(payout.creator_fee, Uint128::new(10));
+    assert_eq!(payout.prizes, vec![Uint128::zero()]);
+  }
+
+  #[test]
+  fn refunds_are_pro_rata_and_last_claimant_gets_the_rest() {
+    let mut pool = RefundPool {
+      amount: Uint128::new(100),
+      total_weight: Uint128::new(3),
+      claimed_weight: Uint128::zero(),
+      claimed_amount: Uint128::zero(),
+      includes_sponsors: false,
+    };
+    for expected in [33u128, 33, 34] {
+      let refund = compute_refund(&pool, Uint128::one());
+      assert_eq!(refund, Uint128::new(expected));
+      pool.claimed_weight += Uint128::one();
+      pool.claimed_amount += refund;
+    }
+    assert_eq!(pool.claimed_amount, pool.amount);
+  }
+
+  #[test]
+  fn referral_payouts_share_what_was_withheld() {
+    let mut game = build_game(0, 0, &[10000], 1);
+    game.referred_amount = Uint128::new(30);
+    game.referral_balance = Uint128::new(10);
+    assert_eq!(compute_referral_payout(&game, Uint128::new(20)), Uint128::new(6));
+
+    // the last referrer to claim gets the rest
+    game.referred_amount = Uint128::new(10);
+    game.referral_balance = Uint128::new(4);
+    assert_eq!(compute_referral_payout(&game, Uint128::new(10)), Uint128::new(4));
+  }
+}
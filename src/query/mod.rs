@@ -1 +1,13 @@
+pub mod query_cell;
+pub mod query_claimable;
+pub mod query_contributions;
 pub mod query_game;
+pub mod query_grid;
+pub mod query_ledger;
+pub mod query_player;
+pub mod query_players;
+pub mod query_referrals;
+
+// default and max number of entries returned by paginated queries
+pub const DEFAULT_LIMIT: u32 = 50;
+pub const MAX_LIMIT: u32 = 100;
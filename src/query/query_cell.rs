@@ -0,0 +1,35 @@
+use cosmwasm_std::{Deps, StdResult};
+
+use crate::{
+  models::GridCoordinates,
+  msg::{CellOwner, CellResponse},
+  nft::load_cell_owners,
+  state::{GAME, GRID},
+};
+
+pub fn query_cell(
+  deps: Deps,
+  coords: GridCoordinates,
+) -> StdResult<CellResponse> {
+  let game = GAME.load(deps.storage)?;
+  let cell = GRID.load(deps.storage, coords)?;
+
+  let owners = load_cell_owners(deps, &game, coords, &cell)?
+    .into_iter()
+    .map(|(wallet, shares)| CellOwner { wallet, shares })
+    .collect();
+
+  let remaining_capacity = game
+    .max_players_per_cell
+    .map(|max_players_per_cell| (max_players_per_cell as u32).saturating_sub(cell.total_shares()));
+
+  let digits = game.digits.as_ref().and_then(|digits| digits.get(coords));
+
+  Ok(CellResponse {
+    coords,
+    cell,
+    owners,
+    remaining_capacity,
+    digits,
+  })
+}
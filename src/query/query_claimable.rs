@@ -0,0 +1,19 @@
+use cosmwasm_std::{Addr, Deps, StdResult};
+
+use crate::{
+  msg::ClaimableResponse,
+  state::{load_referral_payout, CLAIMABLE, GAME},
+};
+
+pub fn query_claimable(
+  deps: Deps,
+  wallet: Addr,
+) -> StdResult<ClaimableResponse> {
+  let game = GAME.load(deps.storage)?;
+  let referral_amount = load_referral_payout(deps.storage, &game, &wallet)?
+    .map(|(_, amount)| amount)
+    .unwrap_or_default();
+  Ok(ClaimableResponse {
+    amount: CLAIMABLE.may_load(deps.storage, wallet)?.unwrap_or_default() + referral_amount,
+  })
+}
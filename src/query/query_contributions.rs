@@ -0,0 +1,21 @@
+use cosmwasm_std::{Addr, Deps, Order, StdResult};
+use cw_storage_plus::Bound;
+
+use super::{DEFAULT_LIMIT, MAX_LIMIT};
+use crate::{msg::ContributionsResponse, state::CONTRIBUTIONS};
+
+pub fn query_contributions(
+  deps: Deps,
+  start_after: Option<Addr>,
+  limit: Option<u32>,
+) -> StdResult<ContributionsResponse> {
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+  let start = start_after.map(Bound::exclusive);
+  Ok(ContributionsResponse {
+    contributions: CONTRIBUTIONS
+      .range(deps.storage, start, None, Order::Ascending)
+      .take(limit)
+      .map(|result| result.map(|(_sponsor, contribution)| contribution))
+      .collect::<StdResult<_>>()?,
+  })
+}
@@ -14,34 +14,28 @@ pub fn query_game(
   Ok(GameResponse {
     game: GAME.load(deps.storage)?,
     players: if with_players.unwrap_or(true) {
-      Some(build_players_vec(deps.storage))
+      Some(build_players_vec(deps.storage)?)
     } else {
       None
     },
     grid: if with_grid.unwrap_or(true) {
-      Some(build_cells_vec(deps.storage))
+      Some(build_cells_vec(deps.storage)?)
     } else {
       None
     },
   })
 }
 
-fn build_cells_vec(storage: &dyn Storage) -> Vec<Cell> {
+fn build_cells_vec(storage: &dyn Storage) -> StdResult<Vec<Cell>> {
   GRID
     .range(storage, None, None, cosmwasm_std::Order::Ascending)
-    .map(|result| -> Cell {
-      let (_coord, cell) = result.unwrap();
-      cell
-    })
+    .map(|result| result.map(|(_coord, cell)| cell))
     .collect()
 }
 
-fn build_players_vec(storage: &dyn Storage) -> Vec<Player> {
+fn build_players_vec(storage: &dyn Storage) -> StdResult<Vec<Player>> {
   PLAYERS
     .range(storage, None, None, cosmwasm_std::Order::Ascending)
-    .map(|result| -> Player {
-      let (_addr, player) = result.unwrap();
-      player
-    })
+    .map(|result| result.map(|(_addr, player)| player))
     .collect()
 }
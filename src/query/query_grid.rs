@@ -0,0 +1,25 @@
+use cosmwasm_std::{Deps, Order, StdResult};
+use cw_storage_plus::Bound;
+
+use super::{DEFAULT_LIMIT, MAX_LIMIT};
+use crate::{
+  models::GridCoordinates,
+  msg::{GridCell, GridResponse},
+  state::GRID,
+};
+
+pub fn query_grid(
+  deps: Deps,
+  start_after: Option<GridCoordinates>,
+  limit: Option<u32>,
+) -> StdResult<GridResponse> {
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+  let start = start_after.map(Bound::exclusive);
+  Ok(GridResponse {
+    cells: GRID
+      .range(deps.storage, start, None, Order::Ascending)
+      .take(limit)
+      .map(|result| result.map(|(coords, cell)| GridCell { coords, cell }))
+      .collect::<StdResult<_>>()?,
+  })
+}
@@ -0,0 +1,18 @@
+use cosmwasm_std::{Coin, Deps, Order, StdResult};
+
+use crate::{
+  msg::LedgerResponse,
+  state::{LEDGER, POTS},
+};
+
+pub fn query_ledger(deps: Deps) -> StdResult<LedgerResponse> {
+  let ledger = LEDGER.may_load(deps.storage)?.unwrap_or_default();
+  Ok(LedgerResponse {
+    unallocated: ledger.unallocated().unwrap_or_default(),
+    pots: POTS
+      .range(deps.storage, None, None, Order::Ascending)
+      .map(|result| result.map(|(denom, amount)| Coin { denom, amount }))
+      .collect::<StdResult<_>>()?,
+    ledger,
+  })
+}
@@ -0,0 +1,53 @@
+use cosmwasm_std::{Addr, Deps, StdResult, Uint128};
+
+use crate::{
+  msg::PlayerResponse,
+  payout::compute_refund,
+  state::{load_referral_payout, CLAIMABLE, CONTRIBUTIONS, GAME, PLAYERS},
+};
+
+pub fn query_player(
+  deps: Deps,
+  wallet: Addr,
+) -> StdResult<PlayerResponse> {
+  let game = GAME.load(deps.storage)?;
+  let player = PLAYERS.may_load(deps.storage, wallet.clone())?;
+  let paid = player.as_ref().and_then(|p| p.paid).unwrap_or_default();
+  let has_claimed_refund = player.as_ref().and_then(|p| p.has_claimed_refund).unwrap_or(false);
+
+  // compute the refund the wallet would get by claiming it now, as a player
+  // and, if sponsors are refunded, as a sponsor
+  let mut refund_amount = Uint128::zero();
+  let some_pool = game
+    .refund_pool
+    .as_ref()
+    .filter(|_| game.can_claim_refund && !game.is_closed);
+  if let Some(pool) = some_pool {
+    let mut weight = Uint128::zero();
+    if player.is_some() && !has_claimed_refund {
+      weight += paid;
+    }
+    if pool.includes_sponsors {
+      if let Some(contribution) = CONTRIBUTIONS.may_load(deps.storage, wallet.clone())? {
+        if !contribution.has_claimed_refund {
+          weight += contribution.amount;
+        }
+      }
+    }
+    if !weight.is_zero() {
+      refund_amount = compute_refund(pool, weight);
+    }
+  }
+
+  let referral_amount = load_referral_payout(deps.storage, &game, &wallet)?
+    .map(|(_, amount)| amount)
+    .unwrap_or_default();
+
+  Ok(PlayerResponse {
+    claimable: CLAIMABLE.may_load(deps.storage, wallet)?.unwrap_or_default() + referral_amount,
+    player,
+    paid,
+    has_claimed_refund,
+    refund_amount,
+  })
+}
@@ -0,0 +1,21 @@
+use cosmwasm_std::{Addr, Deps, Order, StdResult};
+use cw_storage_plus::Bound;
+
+use super::{DEFAULT_LIMIT, MAX_LIMIT};
+use crate::{msg::PlayersResponse, state::PLAYERS};
+
+pub fn query_players(
+  deps: Deps,
+  start_after: Option<Addr>,
+  limit: Option<u32>,
+) -> StdResult<PlayersResponse> {
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+  let start = start_after.map(Bound::exclusive);
+  Ok(PlayersResponse {
+    players: PLAYERS
+      .range(deps.storage, start, None, Order::Ascending)
+      .take(limit)
+      .map(|result| result.map(|(_addr, player)| player))
+      .collect::<StdResult<_>>()?,
+  })
+}
@@ -0,0 +1,29 @@
+use cosmwasm_std::{Addr, Deps, StdResult};
+
+use crate::{
+  msg::ReferralsResponse,
+  payout::compute_referral_payout,
+  state::{GAME, REFERRALS},
+};
+
+pub fn query_referrals(
+  deps: Deps,
+  referrer: Addr,
+) -> StdResult<ReferralsResponse> {
+  let game = GAME.load(deps.storage)?;
+  let totals = REFERRALS.may_load(deps.storage, referrer.clone())?.unwrap_or_default();
+
+  // rewards are the amount paid once claimed, or the referrer's share of the
+  // rewards withheld from house fees so far
+  let rewards = match totals.claimed_rewards {
+    Some(amount) => amount,
+    None if totals.referred_spend.is_zero() => Default::default(),
+    None => compute_referral_payout(&game, totals.referred_spend),
+  };
+
+  Ok(ReferralsResponse {
+    referrer,
+    totals,
+    rewards,
+  })
+}
@@ -2,15 +2,38 @@ use std::collections::HashSet;
 
 use crate::{
   error::ContractError,
-  models::{Cell, Game, Player, Quarter},
-  msg::InstantiateMsg,
+  models::{
+    Cell, CellToken, Contribution, CreatorFee, FeeConfig, FeeRecipient, Game, InviteCode, Ledger, Player, Quarter,
+    ReferralTotals, SponsorRefundPolicy, Token, UnsoldSeedPolicy,
+  },
+  msg::{FeeConfigQueryMsg, FeeConfigResponse, InstantiateMsg},
+  payout::compute_referral_payout,
+  treasury::deposit,
+  util::{decode_sha256_hex, normalize_player_color, validate_player_name, verify_native_payment, BPS_DENOMINATOR},
 };
-use cosmwasm_std::{Addr, DepsMut, Env, MessageInfo, Uint128};
+use cosmwasm_std::{Addr, Api, Deps, DepsMut, Env, MessageInfo, StdResult, Storage, Uint128};
 use cw_storage_plus::{Item, Map};
+use cw_utils::nonpayable;
 
 pub const GAME: Item<Game> = Item::new("game");
 pub const PLAYERS: Map<Addr, Player> = Map::new("players");
 pub const GRID: Map<(u8, u8), Cell> = Map::new("grid");
+pub const INVITE_CODES: Map<String, InviteCode> = Map::new("invite_codes");
+pub const PLAYER_COLORS: Map<String, Addr> = Map::new("player_colors");
+pub const CLAIMABLE: Map<Addr, Uint128> = Map::new("claimable");
+pub const REFERRALS: Map<Addr, ReferralTotals> = Map::new("referrals");
+pub const CONTRIBUTIONS: Map<Addr, Contribution> = Map::new("contributions");
+pub const LEDGER: Item<Ledger> = Item::new("ledger");
+pub const POTS: Map<String, Uint128> = Map::new("pots");
+pub const CELL_TOKENS: Map<(u8, u8), Vec<CellToken>> = Map::new("cell_tokens");
+pub const NFT_TOKEN_COUNT: Item<u64> = Item::new("nft_token_count");
+
+// maximum creator fee allowed by the platform, in basis points
+pub const MAX_CREATOR_FEE_BPS: u16 = 1_000;
+
+// minimum time winners have to claim before unclaimed funds can be swept, in
+// seconds
+pub const MIN_CLAIM_PERIOD: u64 = 7 * 24 * 60 * 60;
 
 /// Initialize contract state data.
 pub fn initialize(
@@ -20,57 +43,55 @@ pub fn initialize(
   msg: &InstantiateMsg,
 ) -> Result<(), ContractError> {
   let mut player_wallets: HashSet<Addr> = HashSet::with_capacity(1);
+  let unique_colors = msg.unique_colors.unwrap_or(false);
 
-  // save each player, preventing duplicate wallet addresses
+  // save each player, preventing duplicate wallet addresses. internal player
+  // fields are forced to their defaults.
   if let Some(players) = &msg.players {
-    for player in players.clone().iter_mut() {
-      if player_wallets.contains(&player.wallet) {
-        return Err(ContractError::DuplicatePlayerAddress {});
-      }
-
-      // force default internal player fields:
-      player.has_claimed_refund = Some(false);
-      player.positions = None;
-
+    for player in players.iter() {
+      save_new_player(
+        deps.storage,
+        &player.wallet,
+        player.name.clone(),
+        player.color.clone(),
+        unique_colors,
+      )?;
       player_wallets.insert(player.wallet.clone());
-
-      PLAYERS.save(deps.storage, player.wallet.clone(), player)?;
     }
   }
 
   // ensure game creator exists as a player
   if !player_wallets.contains(&info.sender) {
-    PLAYERS.save(
-      deps.storage,
-      info.sender.clone(),
-      &Player {
-        wallet: info.sender.clone(),
-        has_claimed_refund: Some(false),
-        positions: None,
-        name: None,
-        color: None,
-      },
-    )?;
+    save_new_player(deps.storage, &info.sender, None, None, unique_colors)?;
   }
 
   // validate quarters
   if msg.quarters.len() != 4 {
     return Err(ContractError::InsufficientQuarters {});
   }
-  let mut total_pct = 0u8;
+  let mut total_bps = 0u32;
   let mut quarters: Vec<Quarter> = Vec::with_capacity(2);
   for quarter in msg.quarters.iter() {
     let mut validated_quarter = quarter.clone();
     validated_quarter.winner = None;
-    quarters.push(validated_quarter);
-    total_pct += quarter.pct;
-    if total_pct > 100 {
+    validated_quarter.winners = None;
+    // convert the deprecated whole-percent split to basis points. a quarter
+    // can't specify both.
+    if let Some(pct) = validated_quarter.pct.take() {
+      if validated_quarter.bps != 0 || pct > 100 {
+        return Err(ContractError::InvalidQuarterSplit {});
+      }
+      validated_quarter.bps = pct as u16 * 100;
+    }
+    total_bps += validated_quarter.bps as u32;
+    if total_bps > BPS_DENOMINATOR as u32 {
       return Err(ContractError::InvalidQuarterSplit {});
     }
+    quarters.push(validated_quarter);
   }
 
-  // the total split shouldn't be less or greater than 100
-  if total_pct != 100 {
+  // the total split shouldn't be less or greater than 100%
+  if total_bps != BPS_DENOMINATOR as u32 {
     return Err(ContractError::InvalidQuarterSplit {});
   }
 
@@ -85,11 +106,21 @@ pub fn initialize(
       return Err(ContractError::InvalidGridCellPrice {});
     }
     // validate player addresses
-    if let Some(cell_player_addrs) = &cell.wallets {
-      for player_addr in cell_player_addrs.iter() {
-        if !player_wallets.contains(&player_addr) {
-          return Err(ContractError::UnknownPlayerAddress {});
+    let n_wallets = match &cell.wallets {
+      Some(cell_player_addrs) => {
+        for player_addr in cell_player_addrs.iter() {
+          if !player_wallets.contains(player_addr) {
+            return Err(ContractError::UnknownPlayerAddress {});
+          }
         }
+        cell_player_addrs.len()
+      },
+      None => 0,
+    };
+    // validate share counts, given for each player address
+    if let Some(shares) = &cell.shares {
+      if shares.len() != n_wallets || shares.contains(&0) {
+        return Err(ContractError::InvalidShareCount {});
       }
     }
     GRID.save(
@@ -98,6 +129,7 @@ pub fn initialize(
       &Cell {
         wallets: cell.wallets.clone(),
         price: cell.price,
+        shares: cell.shares.clone(),
       },
     )?;
   }
@@ -107,27 +139,223 @@ pub fn initialize(
     return Err(ContractError::InvalidTeamCount {});
   }
 
+  // validate merkle root of the wallet allowlist, which must be a hex-encoded
+  // SHA-256 hash
+  if let Some(merkle_root) = &msg.merkle_root {
+    if decode_sha256_hex(merkle_root).is_none() {
+      return Err(ContractError::InvalidMerkleRoot {});
+    }
+  }
+
+  let fees = validate_fee_config(deps.api, &msg.fees)?;
+  let fee_config_contract = match &msg.fee_config_contract {
+    Some(addr) => Some(deps.api.addr_validate(addr.as_str())?),
+    None => None,
+  };
+
+  // validate the creator fee, which goes to the game creator by default
+  let creator_fee = match msg.creator_fee_bps {
+    Some(rate_bps) if rate_bps > 0 => {
+      if rate_bps > MAX_CREATOR_FEE_BPS {
+        return Err(ContractError::InvalidCreatorFee {});
+      }
+      Some(CreatorFee {
+        rate_bps,
+        recipient: match &msg.creator_fee_recipient {
+          Some(addr) => deps.api.addr_validate(addr.as_str())?,
+          None => info.sender.clone(),
+        },
+      })
+    },
+    _ => None,
+  };
+
+  // validate the share of the house fee paid to referrers
+  let referral_bps = msg.referral_bps.unwrap_or(0);
+  if referral_bps > BPS_DENOMINATOR {
+    return Err(ContractError::InvalidReferralShare {});
+  }
+
+  // validate the denoms accepted by games that accept multiple native denoms
+  if let Token::Natives { denoms } = &msg.token {
+    let unique_denoms: HashSet<&String> = denoms.iter().map(|d| &d.denom).collect();
+    if denoms.is_empty() || unique_denoms.len() != denoms.len() || denoms.iter().any(|d| d.rate.is_zero()) {
+      return Err(ContractError::InvalidAcceptedDenoms {});
+    }
+  }
+
+  // unclaimed funds can only be swept to a treasury, after giving winners
+  // at least the platform's minimum claim period
+  let treasury = match &msg.treasury {
+    Some(addr) => Some(deps.api.addr_validate(addr.as_str())?),
+    None => None,
+  };
+  if let Some(claim_period) = msg.claim_period {
+    if claim_period < MIN_CLAIM_PERIOD || treasury.is_none() {
+      return Err(ContractError::InvalidClaimPeriod {});
+    }
+  }
+
+  // the guaranteed seed pool must be sent in the instantiate tx's funds. it's
+  // only supported for games played with native tokens.
+  let seed_amount = msg.seed_amount.unwrap_or_default();
+  if seed_amount.is_zero() {
+    nonpayable(info)?;
+  } else {
+    for coin in verify_native_payment(info, seed_amount, &msg.token)?.iter() {
+      deposit(deps.storage, &msg.token, coin)?;
+    }
+  }
+
   // save validated game data
   GAME.save(
     deps.storage,
     &Game {
       admin: info.sender.clone(),
       name: msg.name.clone(),
-      is_public: msg.is_public.clone(),
+      is_public: msg.is_public,
+      merkle_root: msg.merkle_root.clone(),
       teams: msg.teams.clone(),
+      digits: None,
       token: msg.token.clone(),
       max_players_per_cell: msg.max_players_per_cell,
+      unique_colors,
+      push_payouts: msg.push_payouts.unwrap_or(false),
       has_started: false,
       can_claim_refund: false,
-      token_amount: Uint128::zero(),
+      token_amount: seed_amount,
+      distributed_amount: Uint128::zero(),
+      fees,
+      fee_config_contract,
+      creator_fee,
+      referral_bps,
+      referred_amount: Uint128::zero(),
+      referral_balance: Uint128::zero(),
+      sponsor_amount: Uint128::zero(),
+      sponsor_refund_policy: msg
+        .sponsor_refund_policy
+        .clone()
+        .unwrap_or(SponsorRefundPolicy::ReturnToSponsors),
+      seed_amount,
+      seed_balance: seed_amount,
+      unsold_seed_policy: msg.unsold_seed_policy.clone().unwrap_or(UnsoldSeedPolicy::Rollover),
+      refund_pool: None,
+      nft_contract: None,
+      claim_period: msg.claim_period,
+      claim_deadline: None,
+      treasury,
+      is_closed: false,
+      sweep: None,
+      summary: None,
       quarter_index: 0,
       quarters,
     },
   )?;
 
+  LEDGER.save(
+    deps.storage,
+    &Ledger {
+      collected: seed_amount,
+      ..Ledger::default()
+    },
+  )?;
+
   Ok(())
 }
 
+/// Validate the house fee rate and the address of each fee recipient. If there
+/// is a fee, there must be at least one recipient with a nonzero weight.
+pub fn validate_fee_config(
+  api: &dyn Api,
+  fees: &FeeConfig,
+) -> Result<FeeConfig, ContractError> {
+  if fees.rate_bps > BPS_DENOMINATOR {
+    return Err(ContractError::InvalidFeeConfig {});
+  }
+  let total_weight: u32 = fees.recipients.iter().map(|r| r.weight as u32).sum();
+  if fees.rate_bps > 0 && total_weight == 0 {
+    return Err(ContractError::InvalidFeeConfig {});
+  }
+  let mut recipients: Vec<FeeRecipient> = Vec::with_capacity(fees.recipients.len());
+  for recipient in fees.recipients.iter() {
+    recipients.push(FeeRecipient {
+      address: api.addr_validate(recipient.address.as_str())?,
+      weight: recipient.weight,
+    });
+  }
+  Ok(FeeConfig {
+    rate_bps: fees.rate_bps,
+    recipients,
+  })
+}
+
+/// Get the house fee configuration currently in effect for the game. If the
+/// game points at an external fee config contract, it's queried for the
+/// configuration. If the query fails or returns an invalid configuration, the
+/// game's own configuration is used instead. Returns whether the external
+/// configuration was used.
+pub fn load_fee_config(
+  deps: Deps,
+  game: &Game,
+) -> (FeeConfig, bool) {
+  if let Some(fee_config_contract) = &game.fee_config_contract {
+    let result: Result<FeeConfigResponse, _> = deps
+      .querier
+      .query_wasm_smart(fee_config_contract.clone(), &FeeConfigQueryMsg::FeeConfig {});
+    if let Ok(resp) = result {
+      let fees = FeeConfig {
+        rate_bps: resp.rate_bps,
+        recipients: resp.recipients,
+      };
+      if let Ok(fees) = validate_fee_config(deps.api, &fees) {
+        return (fees, true);
+      }
+    }
+  }
+  (game.fees.clone(), false)
+}
+
+/// Apply changes to the ledger, failing if the funds paid out, taxed, refunded
+/// or owed by the contract would exceed the funds it has collected.
+pub fn update_ledger<F>(
+  storage: &mut dyn Storage,
+  update: F,
+) -> Result<Ledger, ContractError>
+where
+  F: FnOnce(&mut Ledger),
+{
+  let mut ledger = LEDGER.may_load(storage)?.unwrap_or_default();
+  update(&mut ledger);
+  if ledger.unallocated().is_none() {
+    return Err(ContractError::LedgerImbalance {});
+  }
+  LEDGER.save(storage, &ledger)?;
+  Ok(ledger)
+}
+
+/// Load the referral totals of a wallet with referral rewards it can claim,
+/// along with the amount it would be paid for them. Referral rewards can only
+/// be claimed once the game is over, when every quarter's house fee has been
+/// charged.
+pub fn load_referral_payout(
+  storage: &dyn Storage,
+  game: &Game,
+  wallet: &Addr,
+) -> StdResult<Option<(ReferralTotals, Uint128)>> {
+  if !game.is_over() {
+    return Ok(None);
+  }
+  Ok(
+    REFERRALS
+      .may_load(storage, wallet.clone())?
+      .filter(|totals| totals.claimed_rewards.is_none() && !totals.referred_spend.is_zero())
+      .map(|totals| {
+        let amount = compute_referral_payout(game, totals.referred_spend);
+        (totals, amount)
+      }),
+  )
+}
+
 pub fn require_admin(
   game: &Game,
   addr: &Addr,
@@ -137,3 +365,58 @@ pub fn require_admin(
   }
   Ok(())
 }
+
+/// Save a new player with default internal fields, failing if a player with
+/// the same wallet address already exists. The player's name and color are
+/// validated, and if colors must be unique, the color is reserved.
+pub fn save_new_player(
+  storage: &mut dyn Storage,
+  wallet: &Addr,
+  name: Option<String>,
+  color: Option<String>,
+  unique_colors: bool,
+) -> Result<Player, ContractError> {
+  if let Some(name) = &name {
+    validate_player_name(name)?;
+  }
+  let color = color.as_deref().map(normalize_player_color).transpose()?;
+  if unique_colors {
+    if let Some(color) = &color {
+      reserve_player_color(storage, color, wallet)?;
+    }
+  }
+  PLAYERS.update(
+    storage,
+    wallet.clone(),
+    |some_player| -> Result<Player, ContractError> {
+      if some_player.is_none() {
+        Ok(Player {
+          wallet: wallet.clone(),
+          positions: None,
+          has_claimed_refund: Some(false),
+          referrer: None,
+          paid: None,
+          name,
+          color,
+        })
+      } else {
+        Err(ContractError::DuplicatePlayerAddress {})
+      }
+    },
+  )
+}
+
+/// Reserve a normalized player color for the given wallet, failing if another
+/// player already has it.
+pub fn reserve_player_color(
+  storage: &mut dyn Storage,
+  color: &str,
+  wallet: &Addr,
+) -> Result<Addr, ContractError> {
+  PLAYER_COLORS.update(storage, color.to_owned(), |some_owner| -> Result<Addr, ContractError> {
+    match some_owner {
+      Some(owner) if owner != *wallet => Err(ContractError::DuplicatePlayerColor {}),
+      _ => Ok(wallet.clone()),
+    }
+  })
+}
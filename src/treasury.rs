@@ -0,0 +1,107 @@
+use cosmwasm_std::{Addr, BankMsg, Coin, Order, StdResult, Storage, SubMsg, Uint128};
+
+use crate::{
+  error::ContractError,
+  models::{Game, Token},
+  state::{LEDGER, POTS},
+  util::build_token_transfer_msg,
+};
+
+/// Pays out amounts given in the game's base units in the game's token. Games
+/// that accept multiple native denoms keep a separate pot for each denom, and
+/// each amount is paid out of every pot in proportion to the funds held in it.
+pub struct Treasury {
+  token: Token,
+  pots: Vec<Coin>,
+  held_amount: Uint128,
+}
+
+impl Treasury {
+  pub fn load(
+    storage: &dyn Storage,
+    game: &Game,
+  ) -> Result<Self, ContractError> {
+    let ledger = LEDGER.may_load(storage)?.unwrap_or_default();
+    let pots: Vec<Coin> = POTS
+      .range(storage, None, None, Order::Ascending)
+      .map(|result| result.map(|(denom, amount)| Coin { denom, amount }))
+      .collect::<StdResult<_>>()?;
+    Ok(Self {
+      token: game.token.clone(),
+      pots,
+      held_amount: ledger.held(),
+    })
+  }
+
+  /// Build a transfer msg for an amount given in the game's base units, or
+  /// None if there's nothing to send.
+  pub fn build_transfer_msg(
+    &mut self,
+    to_address: &Addr,
+    amount: Uint128,
+  ) -> Result<Option<SubMsg>, ContractError> {
+    if amount.is_zero() {
+      return Ok(None);
+    }
+    if let Token::Natives { .. } = &self.token {
+      let coins = self.withdraw(amount);
+      if coins.is_empty() {
+        return Ok(None);
+      }
+      return Ok(Some(SubMsg::new(BankMsg::Send {
+        to_address: to_address.to_string(),
+        amount: coins,
+      })));
+    }
+    Ok(Some(build_token_transfer_msg(to_address, &self.token, amount)?))
+  }
+
+  /// Save the balance of each pot after withdrawals.
+  pub fn save(
+    &self,
+    storage: &mut dyn Storage,
+  ) -> StdResult<()> {
+    for pot in self.pots.iter() {
+      POTS.save(storage, pot.denom.clone(), &pot.amount)?;
+    }
+    Ok(())
+  }
+
+  /// Withdraw an amount, given in base units, from the pots, in proportion to
+  /// the funds held in each. Withdrawing everything that's held empties them.
+  fn withdraw(
+    &mut self,
+    amount: Uint128,
+  ) -> Vec<Coin> {
+    let is_final_withdrawal = amount >= self.held_amount;
+    let mut coins: Vec<Coin> = Vec::with_capacity(self.pots.len());
+    for pot in self.pots.iter_mut() {
+      let coin_amount = if is_final_withdrawal {
+        pot.amount
+      } else {
+        pot.amount.multiply_ratio(amount, self.held_amount)
+      };
+      if !coin_amount.is_zero() {
+        pot.amount -= coin_amount;
+        coins.push(Coin::new(coin_amount.u128(), pot.denom.clone()));
+      }
+    }
+    self.held_amount = self.held_amount.saturating_sub(amount);
+    coins
+  }
+}
+
+/// Add a coin paid into the game to its denom's pot. Only games that accept
+/// multiple native denoms keep pots.
+pub fn deposit(
+  storage: &mut dyn Storage,
+  token: &Token,
+  coin: &Coin,
+) -> StdResult<()> {
+  if let Token::Natives { .. } = token {
+    POTS.update(storage, coin.denom.clone(), |some_amount| -> StdResult<_> {
+      Ok(some_amount.unwrap_or_default() + coin.amount)
+    })?;
+  }
+  Ok(())
+}
@@ -1,13 +1,27 @@
-use cosmwasm_std::{to_binary, Addr, BankMsg, Coin, CosmosMsg, SubMsg, Uint128, WasmMsg};
+use std::convert::TryFrom;
+
+use cosmwasm_std::{
+  to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, MessageInfo, StdResult, SubMsg, Uint128, Uint256, WasmMsg,
+};
 use cw20::Cw20ExecuteMsg;
+use cw_utils::{must_pay, PaymentError};
+use sha2::{Digest, Sha256};
+
+use crate::{
+  error::ContractError,
+  models::{AcceptedDenom, Token},
+};
 
-use crate::error::ContractError;
+pub const MAX_PLAYER_NAME_LEN: usize = 32;
 
-pub fn compute_amount_from_pct(
+// basis points in 100%
+pub const BPS_DENOMINATOR: u16 = 10_000;
+
+pub fn compute_amount_from_bps(
   total: Uint128,
-  pct: u8,
+  bps: u16,
 ) -> Uint128 {
-  Uint128::from((total.u128() * pct as u128) / 100)
+  total.multiply_ratio(bps, BPS_DENOMINATOR)
 }
 
 // Return a Response that performs a bank transfer of native funds to the
@@ -42,3 +56,333 @@ pub fn build_cw20_transfer_msg(
   });
   Ok(msg)
 }
+
+/// Build transfer message for the game's token, whether native or CW20
+pub fn build_token_transfer_msg(
+  to_address: &Addr,
+  token: &Token,
+  amount: Uint128,
+) -> Result<SubMsg, ContractError> {
+  match token {
+    Token::Native { denom } => Ok(SubMsg::new(build_native_send_msg(to_address, denom, amount)?)),
+    Token::Cw20 { address } => build_cw20_transfer_msg(to_address, address, amount),
+    // amounts must be paid out of each denom's pot, through the treasury
+    Token::Natives { .. } => Err(ContractError::InvalidPaymentMethod {}),
+  }
+}
+
+/// Check that the amount received is exactly the amount expected.
+pub fn verify_amount(
+  expected: Uint128,
+  received: Uint128,
+) -> Result<(), ContractError> {
+  if received < expected {
+    return Err(ContractError::InsufficientFunds { expected, received });
+  } else if received > expected {
+    return Err(ContractError::ExcessFunds { expected, received });
+  }
+  Ok(())
+}
+
+/// Check for the exact payment amount required in the tx's funds, which must
+/// consist of a single coin of the given denom.
+pub fn verify_native_funds(
+  info: &MessageInfo,
+  payment_amount: Uint128,
+  denom: &str,
+) -> Result<(), ContractError> {
+  let received = must_pay(info, denom)?;
+  verify_amount(payment_amount, received)
+}
+
+/// Check for the exact payment required in the tx's funds, given in the game's
+/// base units, returning the coins paid. Games that accept multiple native
+/// denoms accept payment in any combination of them, at their fixed rates.
+pub fn verify_native_payment(
+  info: &MessageInfo,
+  amount: Uint128,
+  token: &Token,
+) -> Result<Vec<Coin>, ContractError> {
+  match token {
+    Token::Native { denom } => {
+      verify_native_funds(info, amount, denom)?;
+      Ok(vec![Coin::new(amount.u128(), denom)])
+    },
+    Token::Natives { denoms } => {
+      let coins = find_accepted_coins(info, denoms)?;
+
+      // the price in each denom is rounded up, in the contract's favor, so
+      // each coin pays for a range of base amounts. the payment is exact if
+      // the amount is within the range the coins pay for combined, and each
+      // coin is the price of at least one whole base amount. coins are never
+      // worth less than the base amount they pay for.
+      let mut min_amount = Uint128::zero();
+      let mut max_amount = Uint128::zero();
+      let mut is_whole = true;
+      for (coin, accepted_denom) in coins.iter() {
+        let (coin_min_amount, coin_max_amount) = compute_base_amount_range(coin.amount, accepted_denom.rate)?;
+        is_whole &= coin_min_amount <= coin_max_amount;
+        min_amount += coin_min_amount;
+        max_amount += coin_max_amount;
+      }
+      if max_amount < amount {
+        return Err(ContractError::InsufficientFunds {
+          expected: amount,
+          received: max_amount,
+        });
+      } else if min_amount > amount || !is_whole {
+        return Err(ContractError::ExcessFunds {
+          expected: amount,
+          received: min_amount,
+        });
+      }
+      Ok(coins.into_iter().map(|(coin, _)| coin).collect())
+    },
+    Token::Cw20 { .. } => Err(ContractError::InvalidPaymentMethod {}),
+  }
+}
+
+/// Find the coins sent in the tx's funds, each of which must be in one of the
+/// accepted denoms, along with their accepted denoms.
+pub fn find_accepted_coins(
+  info: &MessageInfo,
+  denoms: &[AcceptedDenom],
+) -> Result<Vec<(Coin, AcceptedDenom)>, ContractError> {
+  if info.funds.is_empty() {
+    return Err(PaymentError::NoFunds {}.into());
+  }
+  let mut coins: Vec<(Coin, AcceptedDenom)> = Vec::with_capacity(info.funds.len());
+  for coin in info.funds.iter() {
+    let accepted_denom = denoms
+      .iter()
+      .find(|d| d.denom == coin.denom)
+      .ok_or_else(|| PaymentError::ExtraDenom(coin.denom.clone()))?;
+    if coin.amount.is_zero() || coins.iter().any(|(c, _)| c.denom == coin.denom) {
+      return Err(ContractError::InvalidPaymentMethod {});
+    }
+    coins.push((coin.clone(), accepted_denom.clone()));
+  }
+  Ok(coins)
+}
+
+/// Convert an amount of an accepted denom into the game's base units.
+pub fn compute_base_amount(
+  amount: Uint128,
+  rate: Decimal,
+) -> Uint128 {
+  amount.multiply_ratio(Decimal::one().atomics(), rate.atomics())
+}
+
+/// Compute the range of base amounts whose price in an accepted denom, which
+/// is rounded up, is the given amount. The range is empty, with a min greater
+/// than its max, if no base amount has that price.
+pub fn compute_base_amount_range(
+  amount: Uint128,
+  rate: Decimal,
+) -> StdResult<(Uint128, Uint128)> {
+  if amount.is_zero() {
+    return Ok((Uint128::zero(), Uint128::zero()));
+  }
+  let one = Uint256::from(Decimal::one().atomics());
+  let rate = Uint256::from(rate.atomics());
+  let min_amount = (Uint256::from(amount - Uint128::one()) * one) / rate + Uint256::one();
+  let max_amount = (Uint256::from(amount) * one) / rate;
+  Ok((Uint128::try_from(min_amount)?, Uint128::try_from(max_amount)?))
+}
+
+/// Compute the hex-encoded SHA-256 hash of some data.
+pub fn sha256_hex(data: &[u8]) -> String {
+  hex::encode(Sha256::digest(data))
+}
+
+/// Decode a hex-encoded SHA-256 hash, like a merkle root or proof node.
+pub fn decode_sha256_hex(hash: &str) -> Option<[u8; 32]> {
+  let mut buf = [0u8; 32];
+  hex::decode_to_slice(hash, &mut buf).ok().map(|_| buf)
+}
+
+/// Verify that a wallet is included in the merkle tree with the given root.
+/// Each leaf is the SHA-256 hash of a wallet address, and each parent is the
+/// hash of its two children, concatenated in ascending order. The proof is
+/// the list of hex-encoded sibling hashes from the leaf up to the root.
+pub fn verify_merkle_proof(
+  root: &str,
+  proof: &[String],
+  wallet: &Addr,
+) -> Result<(), ContractError> {
+  let root = decode_sha256_hex(root).ok_or(ContractError::InvalidMerkleRoot {})?;
+  let mut hash: [u8; 32] = Sha256::digest(wallet.as_bytes()).into();
+
+  for node in proof.iter() {
+    let sibling = decode_sha256_hex(node).ok_or(ContractError::InvalidMerkleProof {})?;
+    let (left, right) = if hash <= sibling {
+      (hash, sibling)
+    } else {
+      (sibling, hash)
+    };
+    hash = Sha256::new().chain_update(left).chain_update(right).finalize().into();
+  }
+
+  if hash != root {
+    return Err(ContractError::InvalidMerkleProof {});
+  }
+  Ok(())
+}
+
+/// Player names must be non-empty, at most MAX_PLAYER_NAME_LEN characters,
+/// and consist of ASCII letters, digits, spaces, and "-", "_" or ".",
+/// without leading or trailing spaces.
+pub fn validate_player_name(name: &str) -> Result<(), ContractError> {
+  let is_valid_char = |c: char| c.is_ascii_alphanumeric() || c == ' ' || c == '-' || c == '_' || c == '.';
+  if name.is_empty() || name.len() > MAX_PLAYER_NAME_LEN || name.trim() != name || !name.chars().all(is_valid_char) {
+    return Err(ContractError::InvalidPlayerName {});
+  }
+  Ok(())
+}
+
+/// Player colors must be hex colors of the form "#rrggbb". Returns the color
+/// in lowercase so that colors can be compared for uniqueness.
+pub fn normalize_player_color(color: &str) -> Result<String, ContractError> {
+  match color.strip_prefix('#') {
+    Some(hex_digits) if hex_digits.len() == 6 && hex_digits.chars().all(|c| c.is_ascii_hexdigit()) => {
+      Ok(color.to_lowercase())
+    },
+    _ => Err(ContractError::InvalidPlayerColor {}),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use cosmwasm_std::{testing::mock_info, Coin, Decimal, Uint128};
+
+  use super::*;
+
+  fn build_token() -> Token {
+    Token::Natives {
+      denoms: vec![
+        AcceptedDenom {
+          denom: "uatom".to_owned(),
+          rate: Decimal::one(),
+        },
+        AcceptedDenom {
+          denom: "uosmo".to_owned(),
+          rate: Decimal::percent(150),
+        },
+      ],
+    }
+  }
+
+  #[test]
+  fn native_payment_can_combine_accepted_denoms() {
+    // 2 base units in uatom and 3 base units at 1.5 uosmo each, rounded up
+    let info = mock_info("buyer", &[Coin::new(2, "uatom"), Coin::new(5, "uosmo")]);
+    let coins = verify_native_payment(&info, Uint128::new(5), &build_token()).unwrap();
+    assert_eq!(coins, info.funds);
+
+    let result = verify_native_payment(&info, Uint128::new(6), &build_token());
+    assert!(matches!(result, Err(ContractError::InsufficientFunds { .. })));
+    let result = verify_native_payment(&info, Uint128::new(4), &build_token());
+    assert!(matches!(result, Err(ContractError::ExcessFunds { .. })));
+  }
+
+  #[test]
+  fn native_payment_rounds_prices_up() {
+    let token = Token::Natives {
+      denoms: vec![AcceptedDenom {
+        denom: "uatom".to_owned(),
+        rate: Decimal::permille(1),
+      }],
+    };
+
+    // 1uatom is worth 1000 base units, so 1500 costs 2uatom
+    let info = mock_info("buyer", &[Coin::new(1, "uatom")]);
+    let result = verify_native_payment(&info, Uint128::new(1500), &token);
+    assert!(matches!(result, Err(ContractError::InsufficientFunds { .. })));
+    let result = verify_native_payment(&info, Uint128::new(1999), &token);
+    assert!(matches!(result, Err(ContractError::InsufficientFunds { .. })));
+
+    let info = mock_info("buyer", &[Coin::new(2, "uatom")]);
+    assert!(verify_native_payment(&info, Uint128::new(1500), &token).is_ok());
+    assert!(verify_native_payment(&info, Uint128::new(1999), &token).is_ok());
+    let result = verify_native_payment(&info, Uint128::new(1000), &token);
+    assert!(matches!(result, Err(ContractError::ExcessFunds { .. })));
+  }
+
+  #[test]
+  fn native_payment_rejects_unaccepted_denoms() {
+    let info = mock_info("buyer", &[Coin::new(2, "uatom"), Coin::new(1, "ujuno")]);
+    let result = verify_native_payment(&info, Uint128::new(3), &build_token());
+    assert!(matches!(result, Err(ContractError::Payment(_))));
+  }
+
+  #[test]
+  fn base_amount_range_matches_rounded_up_prices() {
+    let rate = Decimal::percent(150);
+    assert_eq!(
+      compute_base_amount_range(Uint128::new(5), rate).unwrap(),
+      (Uint128::new(3), Uint128::new(3))
+    );
+    // no base amount costs exactly 4
+    let (min_amount, max_amount) = compute_base_amount_range(Uint128::new(4), rate).unwrap();
+    assert!(min_amount > max_amount);
+    // several base amounts cost exactly 1 at a rate below 1
+    assert_eq!(
+      compute_base_amount_range(Uint128::new(1), Decimal::percent(50)).unwrap(),
+      (Uint128::new(1), Uint128::new(2))
+    );
+  }
+
+  // sha256("alice"), sha256("bob") and sha256("carol")
+  const ALICE_LEAF: &str = "2bd806c97f0e00af1a1fc3328fa763a9269723c8db8fac4f93af71db186d6e90";
+  const BOB_LEAF: &str = "81b637d8fcd2c6da6359e6963113a1170de795e4b725b84d1e0b4cfd9ec58ce9";
+  const CAROL_LEAF: &str = "4c26d9074c27d89ede59270c0ac14b71e071b15239519f75474b2f3ba63481f5";
+  // parent of the alice and bob leaves, and the root of a tree with carol
+  // promoted to the second level
+  const ALICE_BOB_NODE: &str = "cb57721dc3aa8df0eef91989560b053a86be98131f45650bd1c3955e0167ef17";
+  const THREE_LEAF_ROOT: &str = "2b218ed65d2b6091544e15a20d7f356d7f6ca3992908f4b2b15dd75b41f22b1c";
+
+  #[test]
+  fn merkle_proof_for_single_leaf_tree_is_empty() {
+    assert!(verify_merkle_proof(ALICE_LEAF, &[], &Addr::unchecked("alice")).is_ok());
+    let result = verify_merkle_proof(ALICE_LEAF, &[], &Addr::unchecked("bob"));
+    assert!(matches!(result, Err(ContractError::InvalidMerkleProof {})));
+  }
+
+  #[test]
+  fn merkle_proof_for_three_leaf_tree() {
+    let alice_proof = vec![BOB_LEAF.to_owned(), CAROL_LEAF.to_owned()];
+    assert!(verify_merkle_proof(THREE_LEAF_ROOT, &alice_proof, &Addr::unchecked("alice")).is_ok());
+    let bob_proof = vec![ALICE_LEAF.to_owned(), CAROL_LEAF.to_owned()];
+    assert!(verify_merkle_proof(THREE_LEAF_ROOT, &bob_proof, &Addr::unchecked("bob")).is_ok());
+    let carol_proof = vec![ALICE_BOB_NODE.to_owned()];
+    assert!(verify_merkle_proof(THREE_LEAF_ROOT, &carol_proof, &Addr::unchecked("carol")).is_ok());
+  }
+
+  #[test]
+  fn merkle_proof_rejects_wrong_proof() {
+    // a valid proof for another wallet
+    let alice_proof = vec![BOB_LEAF.to_owned(), CAROL_LEAF.to_owned()];
+    let result = verify_merkle_proof(THREE_LEAF_ROOT, &alice_proof, &Addr::unchecked("carol"));
+    assert!(matches!(result, Err(ContractError::InvalidMerkleProof {})));
+
+    // nodes in the wrong order
+    let reversed_proof = vec![CAROL_LEAF.to_owned(), BOB_LEAF.to_owned()];
+    let result = verify_merkle_proof(THREE_LEAF_ROOT, &reversed_proof, &Addr::unchecked("alice"));
+    assert!(matches!(result, Err(ContractError::InvalidMerkleProof {})));
+  }
+
+  #[test]
+  fn merkle_proof_rejects_non_hex_nodes() {
+    let proof = vec!["not hex".to_owned(), CAROL_LEAF.to_owned()];
+    let result = verify_merkle_proof(THREE_LEAF_ROOT, &proof, &Addr::unchecked("alice"));
+    assert!(matches!(result, Err(ContractError::InvalidMerkleProof {})));
+
+    // nodes must be full SHA-256 hashes
+    let proof = vec![BOB_LEAF[..32].to_owned(), CAROL_LEAF.to_owned()];
+    let result = verify_merkle_proof(THREE_LEAF_ROOT, &proof, &Addr::unchecked("alice"));
+    assert!(matches!(result, Err(ContractError::InvalidMerkleProof {})));
+
+    let result = verify_merkle_proof("not hex", &[], &Addr::unchecked("alice"));
+    assert!(matches!(result, Err(ContractError::InvalidMerkleRoot {})));
+  }
+}